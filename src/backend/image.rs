@@ -0,0 +1,70 @@
+use super::{Backend, Placement};
+use crate::{utils::Rectangle, BarustError, Result};
+use cairo::{Format, ImageSurface, IoError, Surface};
+use std::{fs::File, path::Path};
+
+/// Renders the bar to an [ImageSurface], no X server is needed
+#[derive(Debug)]
+pub struct ImageBackend {
+    surface: ImageSurface,
+    default_width: u16,
+}
+
+impl ImageBackend {
+    ///* `width` used when the [Placement] doesn't set one
+    ///* `height` initial height of the image
+    pub fn new(width: u16, height: u16) -> Result<Self> {
+        Ok(Self {
+            surface: ImageSurface::create(Format::ARgb32, width.into(), height.into())?,
+            default_width: width,
+        })
+    }
+
+    /// The image with the last drawn frame
+    pub fn image(&self) -> &ImageSurface {
+        &self.surface
+    }
+}
+
+impl Backend for ImageBackend {
+    fn surface(&self) -> &Surface {
+        &self.surface
+    }
+
+    fn place(&mut self, placement: &Placement) -> Result<Option<Rectangle>> {
+        let width = placement.width.unwrap_or(self.default_width);
+        if self.surface.width() != i32::from(width)
+            || self.surface.height() != i32::from(placement.height)
+        {
+            self.surface =
+                ImageSurface::create(Format::ARgb32, width.into(), placement.height.into())?;
+        }
+        Ok(Some(Rectangle {
+            x: 0,
+            y: 0,
+            width: width.into(),
+            height: placement.height.into(),
+        }))
+    }
+
+    fn show(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn hide(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.surface.flush();
+        Ok(())
+    }
+
+    fn write_png(&self, path: &Path) -> Result<()> {
+        let mut file = File::create(path)?;
+        self.surface.write_to_png(&mut file).map_err(|e| match e {
+            IoError::Cairo(e) => BarustError::from(e),
+            IoError::Io(e) => BarustError::from(e),
+        })
+    }
+}
@@ -0,0 +1,109 @@
+//! Where a [StatusBar](crate::statusbar::StatusBar) is displayed
+//!
+//! [XcbBackend] shows the bar in an X11 dock window, [ImageBackend] renders it
+//! to an image so that it can be drawn without an X server.
+//! [Osd] shows values in a window of its own, on the X connection of the bar,
+//! and so do the [Tooltip] of the widget under the pointer and the [PopupWindow] of a widget.
+
+use crate::{
+    utils::{Key, Monitor, MouseButton, Position, Rectangle},
+    BarustError, Result,
+};
+use async_channel::Receiver;
+use cairo::Surface;
+use std::{path::Path, sync::Arc};
+use xcb::{x::Window, Connection, Xid};
+
+mod image;
+mod osd;
+mod popup;
+mod tooltip;
+mod x11;
+
+pub use image::ImageBackend;
+pub use osd::Osd;
+pub use popup::PopupWindow;
+pub use tooltip::Tooltip;
+pub use x11::XcbBackend;
+
+/// Where the bar should be placed on the screen
+#[derive(Debug, Clone)]
+pub struct Placement {
+    pub position: Position,
+    pub xoff: u16,
+    /// measured from the bottom edge when the bar is at the bottom
+    pub yoff: u16,
+    /// the width of the monitor if `None`
+    pub width: Option<u16>,
+    pub height: u16,
+    /// RandR output, the primary one if `None`
+    pub output: Option<String>,
+}
+
+/// Input and screen events received by a [Backend]
+#[derive(Debug, Clone, Copy)]
+pub enum BarEvent {
+    ButtonPress {
+        button: MouseButton,
+        x: i16,
+        y: i16,
+    },
+    ButtonRelease {
+        button: MouseButton,
+        x: i16,
+        y: i16,
+    },
+    /// The pointer entered or moved over the bar
+    Motion {
+        x: i16,
+        y: i16,
+    },
+    /// The pointer left the bar
+    Leave,
+    /// A button pressed while a popup grabs the pointer, `x` and `y` are relative to the popup
+    /// and outside of it if the click is
+    PopupButtonPress {
+        button: MouseButton,
+        x: i16,
+        y: i16,
+    },
+    /// A key pressed while a popup grabs the keyboard
+    KeyPress(Key),
+    ScreenChange,
+    /// The whole bar has to be painted again
+    Redraw,
+    /// The popup has to be painted again
+    PopupRedraw,
+}
+
+pub trait Backend {
+    /// Surface the bar is drawn on
+    fn surface(&self) -> &Surface;
+    /// Moves and resizes the bar, returns its new geometry
+    /// or `None` if there is nowhere to place it (e.g. the output is disconnected)
+    fn place(&mut self, placement: &Placement) -> Result<Option<Rectangle>>;
+    fn show(&self) -> Result<()>;
+    fn hide(&self) -> Result<()>;
+    /// Called once a frame has been drawn on [Backend::surface]
+    fn flush(&self) -> Result<()>;
+    /// Events of the bar, `None` if the backend doesn't produce any
+    fn events(&self) -> Result<Option<Receiver<BarEvent>>> {
+        Ok(None)
+    }
+    /// Window of the bar, used by widgets that embed other windows
+    fn window(&self) -> Window {
+        Window::none()
+    }
+    /// X connection of the bar, shared with the widgets that create windows
+    fn connection(&self) -> Option<Arc<Connection>> {
+        None
+    }
+    /// Monitor the bar was last placed on
+    fn monitor(&self) -> Option<Monitor> {
+        None
+    }
+    /// Writes the last drawn frame to a PNG file
+    fn write_png(&self, _path: &Path) -> Result<()> {
+        Err(BarustError::Unsupported("write_png"))
+    }
+}
@@ -0,0 +1,253 @@
+use super::x11::{overlay_window, window_surface};
+use crate::{
+    utils::{
+        set_source_rgba, style::rounded_rectangle, Atoms, Color, Monitor, Rectangle, Side,
+        StatusBarInfo,
+    },
+    widgets::WidgetConfig,
+    Result,
+};
+use cairo::{Context, XCBSurface};
+use log::{debug, warn};
+use pango::{FontDescription, Layout};
+use pangocairo::{create_context, show_layout};
+use std::{
+    fmt::Debug,
+    sync::{
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use xcb::{
+    x::{
+        ConfigWindow, ConfigureWindow, DestroyWindow, EventMask, FreeColormap, MapWindow,
+        StackMode, UnmapWindow, Window,
+    },
+    Connection,
+};
+
+/// Duration of the fade out
+const FADE: Duration = Duration::from_millis(300);
+/// Time between two frames of the fade out
+const FRAME: Duration = Duration::from_millis(16);
+
+/// On-screen display, a window centered on a monitor showing an icon and a progress bar
+/// for the `hide_timeout` of its [WidgetConfig], then fading out
+///
+/// The window is drawn by a thread of its own, it is destroyed when the [Osd] is dropped.
+pub struct Osd {
+    sender: Sender<(String, f64)>,
+}
+
+impl Debug for Osd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt("Osd", f)
+    }
+}
+
+impl Osd {
+    ///* `connection` the connection of the bar
+    ///* `monitor` where the window is centered
+    ///* `config` the font, the colors and the [Style](crate::utils::Style) of the window
+    pub fn new(
+        connection: Arc<Connection>,
+        monitor: &Monitor,
+        config: &WidgetConfig,
+    ) -> Result<Self> {
+        let height = (config.font_size * 5.0) as u16;
+        let geometry = Rectangle {
+            width: u32::from(height) * 4,
+            height: height.into(),
+            x: 0,
+            y: 0,
+        };
+        let x = i32::from(monitor.x) + (i32::from(monitor.width) - geometry.width as i32) / 2;
+        let y = i32::from(monitor.y) + (i32::from(monitor.height) - geometry.height as i32) / 2;
+
+        let atoms = Atoms::new(&connection)?;
+        // the Expose events would be read as the ones of a popup
+        let (window, colormap, visual_type) = overlay_window(
+            &connection,
+            atoms._NET_WM_WINDOW_TYPE_NOTIFICATION,
+            EventMask::NO_EVENT,
+        )?;
+        connection.send_and_check_request(&ConfigureWindow {
+            window,
+            value_list: &[
+                ConfigWindow::X(x),
+                ConfigWindow::Y(y),
+                ConfigWindow::Width(geometry.width),
+                ConfigWindow::Height(geometry.height),
+            ],
+        })?;
+
+        let mut style = config.style.clone();
+        style.background = style.background.or(Some(Color::new(0.1, 0.1, 0.1, 0.85)));
+        style.margin = 0;
+        style.separator = None;
+        let painter = Painter {
+            geometry,
+            font: config.font.clone(),
+            font_size: config.font_size * 2.0,
+            fg_color: config.fg_color,
+            style,
+        };
+
+        let (sender, receiver) = channel::<(String, f64)>();
+        let hide_timeout = config.hide_timeout;
+        thread::spawn(move || {
+            let surface = window_surface(
+                &connection,
+                window,
+                visual_type,
+                painter.geometry.width,
+                painter.geometry.height,
+            );
+            let surface = match surface {
+                Ok(surface) => surface,
+                Err(e) => {
+                    warn!("Cannot create the OSD surface: {}", e);
+                    return;
+                }
+            };
+
+            // icon, percentage and the instant they were shown
+            let mut shown: Option<(String, f64, Instant)> = None;
+            loop {
+                let message = match &shown {
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    Some((_, _, since)) => {
+                        let wait = hide_timeout.checked_sub(since.elapsed()).unwrap_or(FRAME);
+                        receiver.recv_timeout(wait)
+                    }
+                };
+                let result = match message {
+                    Ok((icon, percent)) => {
+                        let was_hidden = shown.is_none();
+                        let result = painter.paint(&surface, &icon, percent, 1.0).and_then(|_| {
+                            if was_hidden {
+                                map(&connection, window)
+                            } else {
+                                Ok(())
+                            }
+                        });
+                        shown = Some((icon, percent, Instant::now()));
+                        result
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        let Some((icon, percent, since)) = &shown else {
+                            continue;
+                        };
+                        let fading = since.elapsed().saturating_sub(hide_timeout);
+                        if fading >= FADE {
+                            shown = None;
+                            connection
+                                .send_and_check_request(&UnmapWindow { window })
+                                .map_err(Into::into)
+                        } else {
+                            let alpha = 1.0 - fading.as_secs_f64() / FADE.as_secs_f64();
+                            painter.paint(&surface, icon, *percent, alpha)
+                        }
+                    }
+                    // the widget was dropped
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                if let Err(e) = result.and_then(|_| Ok(connection.flush()?)) {
+                    warn!("Cannot draw the OSD: {}", e);
+                }
+            }
+            debug!("Destroying the OSD window");
+            connection.send_request(&DestroyWindow { window });
+            connection.send_request(&FreeColormap { cmap: colormap });
+            connection.flush().ok();
+        });
+        Ok(Self { sender })
+    }
+
+    /// Creates the [Osd] of a widget on the monitor of the bar,
+    /// `None` if the bar is not displayed by the X server or the window cannot be created
+    pub fn for_bar(info: &StatusBarInfo, config: &WidgetConfig) -> Option<Self> {
+        let (Some(connection), Some(monitor)) = (&info.connection, &info.monitor) else {
+            return None;
+        };
+        Self::new(Arc::clone(connection), monitor, config)
+            .map_err(|e| warn!("Cannot create the OSD window: {}", e))
+            .ok()
+    }
+
+    /// Shows `icon` and `percent`, in a range from 0 to 100, and restarts the timeout
+    pub fn show(&self, icon: impl ToString, percent: f64) {
+        self.sender.send((icon.to_string(), percent)).ok();
+    }
+}
+
+/// Maps the window above the others
+fn map(connection: &Connection, window: Window) -> Result<()> {
+    connection.send_and_check_request(&MapWindow { window })?;
+    connection.send_and_check_request(&ConfigureWindow {
+        window,
+        value_list: &[ConfigWindow::StackMode(StackMode::Above)],
+    })?;
+    Ok(())
+}
+
+struct Painter {
+    geometry: Rectangle,
+    font: String,
+    font_size: f64,
+    fg_color: Color,
+    style: crate::utils::Style,
+}
+
+impl Painter {
+    fn paint(&self, surface: &XCBSurface, icon: &str, percent: f64, alpha: f64) -> Result<()> {
+        let context = Context::new(surface)?;
+        context.push_group();
+        self.style.draw(&context, &self.geometry, Side::Left)?;
+
+        let width = f64::from(self.geometry.width);
+        let height = f64::from(self.geometry.height);
+        let padding = height / 4.0;
+
+        let layout = Layout::new(&create_context(&context).ok_or(cairo::Error::NoMemory)?);
+        let mut font = FontDescription::from_string(&self.font);
+        font.set_absolute_size(self.font_size * f64::from(pango::SCALE));
+        layout.set_font_description(Some(&font));
+        layout.set_text(icon);
+        let (icon_width, icon_height) = layout.pixel_size();
+        set_source_rgba(&context, self.fg_color);
+        context.move_to(padding, (height - f64::from(icon_height)) / 2.0);
+        show_layout(&context, &layout);
+
+        let bar_x = 2.0 * padding + f64::from(icon_width);
+        let bar_width = (width - padding - bar_x).max(0.0);
+        let bar_height = (height / 10.0).max(4.0);
+        let bar_y = (height - bar_height) / 2.0;
+        let track = Color {
+            a: self.fg_color.a * 0.3,
+            ..self.fg_color
+        };
+        set_source_rgba(&context, track);
+        rounded_rectangle(
+            &context,
+            bar_x,
+            bar_y,
+            bar_width,
+            bar_height,
+            bar_height / 2.0,
+        );
+        context.fill()?;
+        set_source_rgba(&context, self.fg_color);
+        let filled = bar_width * percent.clamp(0.0, 100.0) / 100.0;
+        rounded_rectangle(&context, bar_x, bar_y, filled, bar_height, bar_height / 2.0);
+        context.fill()?;
+
+        context.pop_group_to_source()?;
+        context.set_operator(cairo::Operator::Source);
+        context.paint_with_alpha(alpha)?;
+        surface.flush();
+        Ok(())
+    }
+}
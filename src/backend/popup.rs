@@ -0,0 +1,172 @@
+use super::x11::{anchored_position, overlay_window, window_surface};
+use crate::{
+    utils::{Atoms, Monitor, Rectangle},
+    Result,
+};
+use cairo::XCBSurface;
+use log::{debug, warn};
+use std::{fmt::Debug, sync::Arc};
+use xcb::{
+    x::{
+        self, Colormap, ConfigWindow, ConfigureWindow, DestroyWindow, EventMask, FreeColormap,
+        GrabKeyboard, GrabMode, GrabPointer, GrabStatus, MapWindow, StackMode, UngrabKeyboard,
+        UngrabPointer, Window,
+    },
+    Connection, Xid,
+};
+
+/// Window of a [Popup](crate::statusbar::Popup), anchored under a region of the bar
+///
+/// Once shown it grabs the pointer and the keyboard, so that its events and the clicks outside
+/// of it are reported as [PopupButtonPress](super::BarEvent::PopupButtonPress) and
+/// [KeyPress](super::BarEvent::KeyPress). The grabs are released when it is dropped.
+/// Its Expose events are reported as [PopupRedraw](super::BarEvent::PopupRedraw).
+pub struct PopupWindow {
+    connection: Arc<Connection>,
+    bar: Window,
+    window: Window,
+    colormap: Colormap,
+    surface: XCBSurface,
+    monitor: Monitor,
+    region: Rectangle,
+    size: Option<(u32, u32)>,
+}
+
+impl Debug for PopupWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt("PopupWindow", f)
+    }
+}
+
+impl PopupWindow {
+    ///* `connection` the connection of the bar
+    ///* `bar` the window of the bar
+    ///* `monitor` the popup is kept inside of it
+    ///* `region` the region of the bar the popup is anchored to
+    pub fn new(
+        connection: Arc<Connection>,
+        bar: Window,
+        monitor: Monitor,
+        region: Rectangle,
+    ) -> Result<Self> {
+        let atoms = Atoms::new(&connection)?;
+        let (window, colormap, visual_type) = overlay_window(
+            &connection,
+            atoms._NET_WM_WINDOW_TYPE_POPUP_MENU,
+            EventMask::EXPOSURE | EventMask::BUTTON_PRESS | EventMask::KEY_PRESS,
+        )?;
+        let surface = window_surface(&connection, window, visual_type, 1, 1)?;
+        Ok(Self {
+            connection,
+            bar,
+            window,
+            colormap,
+            surface,
+            monitor,
+            region,
+            size: None,
+        })
+    }
+
+    /// Surface the content of the popup is drawn on
+    pub fn surface(&self) -> &XCBSurface {
+        &self.surface
+    }
+
+    /// Width and height of the window, `None` until it is shown
+    pub fn size(&self) -> Option<(u32, u32)> {
+        self.size
+    }
+
+    /// Resizes the window to `width` and `height`, the first call maps it and grabs the input
+    pub fn show(&mut self, width: u32, height: u32) -> Result<()> {
+        if self.size == Some((width, height)) {
+            return Ok(());
+        }
+        let (x, y) = anchored_position(
+            &self.connection,
+            self.bar,
+            &self.region,
+            &self.monitor,
+            (width, height),
+        )?;
+        self.connection.send_and_check_request(&ConfigureWindow {
+            window: self.window,
+            value_list: &[
+                ConfigWindow::X(x),
+                ConfigWindow::Y(y),
+                ConfigWindow::Width(width),
+                ConfigWindow::Height(height),
+                ConfigWindow::StackMode(StackMode::Above),
+            ],
+        })?;
+        self.surface.set_size(width as _, height as _)?;
+        if self.size.replace((width, height)).is_none() {
+            self.connection.send_and_check_request(&MapWindow {
+                window: self.window,
+            })?;
+            self.grab()?;
+        }
+        Ok(())
+    }
+
+    /// Called once the content has been drawn on [PopupWindow::surface]
+    pub fn flush(&self) -> Result<()> {
+        self.surface.flush();
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    fn grab(&self) -> Result<()> {
+        let pointer = self.connection.send_request(&GrabPointer {
+            // the events are reported relative to the popup, wherever the pointer is
+            owner_events: false,
+            grab_window: self.window,
+            event_mask: EventMask::BUTTON_PRESS,
+            pointer_mode: GrabMode::Async,
+            keyboard_mode: GrabMode::Async,
+            confine_to: Window::none(),
+            cursor: x::Cursor::none(),
+            time: x::CURRENT_TIME,
+        });
+        let keyboard = self.connection.send_request(&GrabKeyboard {
+            owner_events: false,
+            grab_window: self.window,
+            time: x::CURRENT_TIME,
+            pointer_mode: GrabMode::Async,
+            keyboard_mode: GrabMode::Async,
+        });
+        for (device, status) in [
+            ("pointer", self.connection.wait_for_reply(pointer)?.status()),
+            (
+                "keyboard",
+                self.connection.wait_for_reply(keyboard)?.status(),
+            ),
+        ] {
+            if status != GrabStatus::Success {
+                // the popup still works, it is closed by a click on the bar instead
+                warn!("Cannot grab the {} for the popup: {:?}", device, status);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PopupWindow {
+    fn drop(&mut self) {
+        debug!("Destroying the popup window");
+        self.connection.send_request(&UngrabPointer {
+            time: x::CURRENT_TIME,
+        });
+        self.connection.send_request(&UngrabKeyboard {
+            time: x::CURRENT_TIME,
+        });
+        self.connection.send_request(&DestroyWindow {
+            window: self.window,
+        });
+        self.connection.send_request(&FreeColormap {
+            cmap: self.colormap,
+        });
+        self.connection.flush().ok();
+    }
+}
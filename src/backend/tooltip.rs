@@ -0,0 +1,172 @@
+use super::x11::{anchored_position, overlay_window, window_surface};
+use crate::{
+    utils::{set_source_rgba, Atoms, Color, Monitor, Rectangle, Side},
+    widgets::WidgetConfig,
+    Result,
+};
+use cairo::{Context, XCBSurface};
+use log::debug;
+use pango::{parse_markup, FontDescription};
+use pangocairo::{create_layout, show_layout};
+use std::{fmt::Debug, sync::Arc};
+use xcb::{
+    x::{
+        Colormap, ConfigWindow, ConfigureWindow, DestroyWindow, EventMask, FreeColormap, MapWindow,
+        StackMode, UnmapWindow, Window,
+    },
+    Connection,
+};
+
+/// Window showing the [tooltip](crate::widgets::Widget::tooltip) of a widget
+/// right below the bar, or above it if there is no room below
+pub struct Tooltip {
+    connection: Arc<Connection>,
+    bar: Window,
+    window: Window,
+    colormap: Colormap,
+    surface: XCBSurface,
+    monitor: Monitor,
+    config: WidgetConfig,
+    visible: bool,
+}
+
+impl Debug for Tooltip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt("Tooltip", f)
+    }
+}
+
+impl Tooltip {
+    ///* `connection` the connection of the bar
+    ///* `bar` the window of the bar, the regions passed to [Tooltip::show] are relative to it
+    ///* `monitor` the tooltip is kept inside of it
+    ///* `config` the font, the colors and the [Style](crate::utils::Style) of the window
+    pub fn new(
+        connection: Arc<Connection>,
+        bar: Window,
+        monitor: Monitor,
+        config: &WidgetConfig,
+    ) -> Result<Self> {
+        let atoms = Atoms::new(&connection)?;
+        // the Expose events would be read as the ones of a popup
+        let (window, colormap, visual_type) = overlay_window(
+            &connection,
+            atoms._NET_WM_WINDOW_TYPE_TOOLTIP,
+            EventMask::NO_EVENT,
+        )?;
+        let surface = window_surface(&connection, window, visual_type, 1, 1)?;
+
+        let mut config = config.clone();
+        config.style.background = config
+            .style
+            .background
+            .or(Some(Color::new(0.1, 0.1, 0.1, 0.9)));
+        config.style.margin = 0;
+        config.style.separator = None;
+        Ok(Self {
+            connection,
+            bar,
+            window,
+            colormap,
+            surface,
+            monitor,
+            config,
+            visible: false,
+        })
+    }
+
+    /// Shows `markup` next to `region`, a region of the bar
+    pub fn show(&mut self, markup: &str, region: &Rectangle) -> Result<()> {
+        let context = Context::new(&self.surface)?;
+        let layout = create_layout(&context).ok_or(cairo::Error::NoMemory)?;
+        let mut font = FontDescription::from_string(&self.config.font);
+        font.set_absolute_size(self.config.font_size * f64::from(pango::SCALE));
+        layout.set_font_description(Some(&font));
+        match parse_markup(markup, '\0') {
+            Ok((attributes, text, _)) => {
+                layout.set_text(&text);
+                layout.set_attributes(Some(&attributes));
+            }
+            Err(e) => {
+                debug!("Invalid tooltip markup {:?}: {}", markup, e);
+                layout.set_text(markup);
+            }
+        }
+        let (text_width, text_height) = layout.pixel_size();
+        let padding = (self.config.font_size / 2.0) as i32;
+        let width = text_width + 2 * padding;
+        let height = text_height + 2 * padding;
+
+        let (x, y) = anchored_position(
+            &self.connection,
+            self.bar,
+            region,
+            &self.monitor,
+            (width as _, height as _),
+        )?;
+
+        self.connection.send_and_check_request(&ConfigureWindow {
+            window: self.window,
+            value_list: &[
+                ConfigWindow::X(x),
+                ConfigWindow::Y(y),
+                ConfigWindow::Width(width as _),
+                ConfigWindow::Height(height as _),
+                ConfigWindow::StackMode(StackMode::Above),
+            ],
+        })?;
+        self.surface.set_size(width, height)?;
+        if !self.visible {
+            self.connection.send_and_check_request(&MapWindow {
+                window: self.window,
+            })?;
+            self.visible = true;
+        }
+
+        let context = Context::new(&self.surface)?;
+        context.set_operator(cairo::Operator::Source);
+        set_source_rgba(&context, Color::new(0.0, 0.0, 0.0, 0.0));
+        context.paint()?;
+        context.set_operator(cairo::Operator::Over);
+        let geometry = Rectangle {
+            x: 0,
+            y: 0,
+            width: width as _,
+            height: height as _,
+        };
+        self.config.style.draw(&context, &geometry, Side::Left)?;
+        set_source_rgba(&context, self.config.fg_color);
+        context.move_to(f64::from(padding), f64::from(padding));
+        show_layout(&context, &layout);
+        self.surface.flush();
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    pub fn hide(&mut self) -> Result<()> {
+        if self.visible {
+            self.connection.send_and_check_request(&UnmapWindow {
+                window: self.window,
+            })?;
+            self.visible = false;
+        }
+        Ok(())
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+}
+
+impl Drop for Tooltip {
+    fn drop(&mut self) {
+        debug!("Destroying the tooltip window");
+        self.connection.send_request(&DestroyWindow {
+            window: self.window,
+        });
+        self.connection.send_request(&FreeColormap {
+            cmap: self.colormap,
+        });
+        self.connection.flush().ok();
+    }
+}
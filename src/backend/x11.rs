@@ -0,0 +1,531 @@
+use super::{Backend, BarEvent, Placement};
+use crate::{
+    utils::{find_monitor, monitors, screen_size, Atoms, Key, Monitor, Position, Rectangle},
+    Result,
+};
+use async_channel::{bounded, Receiver};
+use cairo::{Surface, XCBConnection, XCBDrawable, XCBSurface, XCBVisualType};
+use log::{debug, error};
+use std::{sync::Arc, thread};
+use xcb::{
+    randr,
+    x::{
+        self, Atom, Colormap, ColormapAlloc, ConfigWindow, ConfigureWindow, CreateColormap,
+        CreateWindow, Cw, EventMask, MapWindow, Pixmap, Screen, UnmapWindow, VisualClass,
+        Visualtype, Window, WindowClass,
+    },
+    Connection, Event, Extension, Xid,
+};
+
+/// Displays the bar in a dock window of the X server
+pub struct XcbBackend {
+    connection: Arc<Connection>,
+    screen_id: i32,
+    window: Window,
+    surface: XCBSurface,
+    monitor: Option<Monitor>,
+}
+
+impl XcbBackend {
+    /// Connects to the X server and creates the bar window,
+    /// it stays unmapped until it is placed and shown
+    pub fn new() -> Result<Self> {
+        let (connection, screen_id) =
+            Connection::connect_with_extensions(None, &[], &[Extension::RandR])?;
+        let connection = Arc::new(connection);
+
+        let window: Window = connection.generate_id();
+        let colormap: Colormap = connection.generate_id();
+
+        let screen = connection
+            .get_setup()
+            .roots()
+            .nth(screen_id as _)
+            .unwrap_or_else(|| panic!("cannot find screen:{}", screen_id));
+
+        let mut visual_type = argb_visual(screen);
+
+        connection.send_and_check_request(&CreateColormap {
+            alloc: ColormapAlloc::None,
+            mid: colormap,
+            window: screen.root(),
+            visual: visual_type.visual_id(),
+        })?;
+
+        connection.send_and_check_request(&CreateWindow {
+            depth: 32,
+            wid: window,
+            parent: screen.root(),
+            // moved and resized by place
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+            border_width: 0,
+            class: WindowClass::InputOutput,
+            visual: visual_type.visual_id(),
+            value_list: &[
+                Cw::BackPixmap(Pixmap::none()),
+                Cw::BorderPixel(screen.black_pixel()),
+                Cw::EventMask(EventMask::all()),
+                Cw::Colormap(colormap),
+            ],
+        })?;
+
+        let atoms = Atoms::new(&connection)?;
+        connection.send_and_check_request(&xcb::x::ChangeProperty {
+            mode: xcb::x::PropMode::Replace,
+            window,
+            property: atoms._NET_WM_WINDOW_TYPE,
+            r#type: xcb::x::ATOM_ATOM,
+            data: &[atoms._NET_WM_WINDOW_TYPE_DOCK],
+        })?;
+        connection.send_and_check_request(&xcb::x::ChangeProperty {
+            mode: xcb::x::PropMode::Replace,
+            window,
+            property: atoms._NET_WM_STATE,
+            r#type: xcb::x::ATOM_ATOM,
+            data: &[atoms._NET_WM_STATE_STICKY, atoms._NET_WM_STATE_ABOVE],
+        })?;
+        connection.send_and_check_request(&xcb::x::ChangeProperty {
+            mode: xcb::x::PropMode::Replace,
+            window,
+            property: atoms._NET_WM_DESKTOP,
+            r#type: xcb::x::ATOM_CARDINAL,
+            data: &[0xFFFFFFFF_u32],
+        })?;
+        if connection
+            .active_extensions()
+            .any(|ext| ext == Extension::RandR)
+        {
+            connection.send_and_check_request(&randr::SelectInput {
+                window: screen.root(),
+                enable: randr::NotifyMask::SCREEN_CHANGE
+                    | randr::NotifyMask::CRTC_CHANGE
+                    | randr::NotifyMask::OUTPUT_CHANGE,
+            })?;
+        }
+
+        let surface = unsafe {
+            let conn_ptr = connection.get_raw_conn() as _;
+            XCBSurface::create(
+                &XCBConnection::from_raw_none(conn_ptr),
+                &XCBDrawable(window.resource_id()),
+                &XCBVisualType::from_raw_none(&mut visual_type as *mut Visualtype as _),
+                1,
+                1,
+            )?
+        };
+
+        connection.flush()?;
+
+        Ok(Self {
+            connection,
+            screen_id,
+            window,
+            surface,
+            monitor: None,
+        })
+    }
+}
+
+/// The true color visual with an alpha channel, the windows using it have a depth of 32
+pub(crate) fn argb_visual(screen: &Screen) -> Visualtype {
+    screen
+        .allowed_depths()
+        .find(|d| d.depth() == 32)
+        .expect("cannot find valid depth")
+        .visuals()
+        .iter()
+        .find(|v| v.class() == VisualClass::TrueColor)
+        .expect("cannot find valid visual type")
+        .to_owned()
+}
+
+impl Backend for XcbBackend {
+    fn surface(&self) -> &Surface {
+        &self.surface
+    }
+
+    fn place(&mut self, placement: &Placement) -> Result<Option<Rectangle>> {
+        let monitor = find_monitor(
+            monitors(&self.connection, self.screen_id)?,
+            placement.output.as_deref(),
+        );
+        let Some(monitor) = monitor else {
+            return Ok(None);
+        };
+
+        let geometry = bar_geometry(&monitor, placement);
+        self.monitor = Some(monitor);
+        debug!("Placing bar at {:?}", geometry);
+        self.connection.send_and_check_request(&ConfigureWindow {
+            window: self.window,
+            value_list: &[
+                ConfigWindow::X(geometry.x as _),
+                ConfigWindow::Y(geometry.y as _),
+                ConfigWindow::Width(geometry.width),
+                ConfigWindow::Height(geometry.height),
+            ],
+        })?;
+        self.surface
+            .set_size(geometry.width as _, geometry.height as _)?;
+        let (_, screen_height) = screen_size(&self.connection, self.screen_id)?;
+        set_struts(
+            &self.connection,
+            self.window,
+            placement.position,
+            geometry,
+            screen_height.into(),
+        )?;
+        Ok(Some(geometry))
+    }
+
+    fn show(&self) -> Result<()> {
+        self.connection.send_and_check_request(&MapWindow {
+            window: self.window,
+        })?;
+        Ok(())
+    }
+
+    /// Unmaps the bar and releases the space reserved for it
+    fn hide(&self) -> Result<()> {
+        self.connection.send_and_check_request(&UnmapWindow {
+            window: self.window,
+        })?;
+        clear_struts(&self.connection, self.window)
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.surface.flush();
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    fn events(&self) -> Result<Option<Receiver<BarEvent>>> {
+        bar_event_listener(Arc::clone(&self.connection), self.window).map(Some)
+    }
+
+    fn window(&self) -> Window {
+        self.window
+    }
+
+    fn connection(&self) -> Option<Arc<Connection>> {
+        Some(Arc::clone(&self.connection))
+    }
+
+    fn monitor(&self) -> Option<Monitor> {
+        self.monitor.clone()
+    }
+}
+
+/// Creates an unmapped window with an alpha channel, not managed by the window manager,
+/// for the windows shown next to the bar, `window_type` tells the compositor how to treat it,
+/// the colormap of the window has to be freed once it is destroyed
+pub(crate) fn overlay_window(
+    connection: &Connection,
+    window_type: Atom,
+    event_mask: EventMask,
+) -> Result<(Window, Colormap, Visualtype)> {
+    let screen = connection
+        .get_setup()
+        .roots()
+        .next()
+        .expect("cannot find screen");
+    let visual_type = argb_visual(screen);
+    let window: Window = connection.generate_id();
+    let colormap: Colormap = connection.generate_id();
+    connection.send_and_check_request(&CreateColormap {
+        alloc: ColormapAlloc::None,
+        mid: colormap,
+        window: screen.root(),
+        visual: visual_type.visual_id(),
+    })?;
+    connection.send_and_check_request(&CreateWindow {
+        depth: 32,
+        wid: window,
+        parent: screen.root(),
+        // moved and resized once its content is known
+        x: 0,
+        y: 0,
+        width: 1,
+        height: 1,
+        border_width: 0,
+        class: WindowClass::InputOutput,
+        visual: visual_type.visual_id(),
+        value_list: &[
+            Cw::BackPixmap(Pixmap::none()),
+            Cw::BorderPixel(screen.black_pixel()),
+            Cw::OverrideRedirect(true),
+            Cw::EventMask(event_mask),
+            Cw::Colormap(colormap),
+        ],
+    })?;
+    let atoms = Atoms::new(connection)?;
+    connection.send_and_check_request(&x::ChangeProperty {
+        mode: x::PropMode::Replace,
+        window,
+        property: atoms._NET_WM_WINDOW_TYPE,
+        r#type: x::ATOM_ATOM,
+        data: &[window_type],
+    })?;
+    Ok((window, colormap, visual_type))
+}
+
+/// The cairo surface of a window created by [overlay_window]
+pub(crate) fn window_surface(
+    connection: &Connection,
+    window: Window,
+    mut visual_type: Visualtype,
+    width: u32,
+    height: u32,
+) -> Result<XCBSurface> {
+    let surface = unsafe {
+        let conn_ptr = connection.get_raw_conn() as _;
+        XCBSurface::create(
+            &XCBConnection::from_raw_none(conn_ptr),
+            &XCBDrawable(window.resource_id()),
+            &XCBVisualType::from_raw_none(&mut visual_type as *mut Visualtype as _),
+            width as _,
+            height as _,
+        )?
+    };
+    Ok(surface)
+}
+
+/// Position of a window of `width` and `height` next to `region` of the bar `window`,
+/// below the region unless there is no room below it, and kept inside `monitor`
+pub(crate) fn anchored_position(
+    connection: &Connection,
+    window: Window,
+    region: &Rectangle,
+    monitor: &Monitor,
+    (width, height): (u32, u32),
+) -> Result<(i32, i32)> {
+    let root = connection
+        .get_setup()
+        .roots()
+        .next()
+        .expect("cannot find screen")
+        .root();
+    let cookie = connection.send_request(&x::TranslateCoordinates {
+        src_window: window,
+        dst_window: root,
+        src_x: region.x as _,
+        src_y: region.y as _,
+    });
+    let origin = connection.wait_for_reply(cookie)?;
+    let (x, y) = (i32::from(origin.dst_x()), i32::from(origin.dst_y()));
+    let (width, height) = (width as i32, height as i32);
+    let monitor_left = i32::from(monitor.x);
+    let monitor_right = monitor_left + i32::from(monitor.width);
+    let monitor_bottom = i32::from(monitor.y) + i32::from(monitor.height);
+    let x = x.min(monitor_right - width).max(monitor_left);
+    let below = y + region.height as i32;
+    if below + height <= monitor_bottom {
+        Ok((x, below))
+    } else {
+        Ok((x, y - height))
+    }
+}
+
+/// Computes the position and size of the bar inside `monitor`
+fn bar_geometry(monitor: &Monitor, placement: &Placement) -> Rectangle {
+    let Placement {
+        position,
+        xoff,
+        yoff,
+        width,
+        height,
+        ..
+    } = *placement;
+    let x = i32::from(monitor.x) + i32::from(xoff);
+    let y = match position {
+        Position::Top => i32::from(monitor.y) + i32::from(yoff),
+        Position::Bottom => {
+            i32::from(monitor.y) + i32::from(monitor.height) - i32::from(height) - i32::from(yoff)
+        }
+    };
+    Rectangle {
+        x: x.max(0) as _,
+        y: y.max(0) as _,
+        width: width.unwrap_or(monitor.width).into(),
+        height: height.into(),
+    }
+}
+
+/// Reserves the space used by the bar so that other windows don't overlap it
+fn set_struts(
+    connection: &Connection,
+    window: Window,
+    position: Position,
+    geometry: Rectangle,
+    screen_height: u32,
+) -> Result<()> {
+    let atoms = Atoms::new(connection)?;
+    let start_x = geometry.x;
+    let end_x = geometry.x + geometry.width.saturating_sub(1);
+    // left, right, top, bottom
+    let mut strut = [0_u32; 4];
+    // left_start_y, left_end_y, right_start_y, right_end_y,
+    // top_start_x, top_end_x, bottom_start_x, bottom_end_x
+    let mut ranges = [0_u32; 8];
+    match position {
+        Position::Top => {
+            strut[2] = geometry.y + geometry.height;
+            ranges[4] = start_x;
+            ranges[5] = end_x;
+        }
+        Position::Bottom => {
+            strut[3] = screen_height.saturating_sub(geometry.y);
+            ranges[6] = start_x;
+            ranges[7] = end_x;
+        }
+    }
+    let strut_partial: Vec<u32> = strut.iter().chain(&ranges).copied().collect();
+
+    connection.send_and_check_request(&xcb::x::ChangeProperty {
+        mode: xcb::x::PropMode::Replace,
+        window,
+        property: atoms._NET_WM_STRUT,
+        r#type: xcb::x::ATOM_CARDINAL,
+        data: &strut,
+    })?;
+    connection.send_and_check_request(&xcb::x::ChangeProperty {
+        mode: xcb::x::PropMode::Replace,
+        window,
+        property: atoms._NET_WM_STRUT_PARTIAL,
+        r#type: xcb::x::ATOM_CARDINAL,
+        data: &strut_partial,
+    })?;
+    Ok(())
+}
+
+fn clear_struts(connection: &Connection, window: Window) -> Result<()> {
+    let atoms = Atoms::new(connection)?;
+    for (property, len) in [(atoms._NET_WM_STRUT, 4), (atoms._NET_WM_STRUT_PARTIAL, 12)] {
+        connection.send_and_check_request(&xcb::x::ChangeProperty {
+            mode: xcb::x::PropMode::Replace,
+            window,
+            property,
+            r#type: xcb::x::ATOM_CARDINAL,
+            data: &vec![0_u32; len],
+        })?;
+    }
+    Ok(())
+}
+
+/// Translates the X events the bar cares about, the others are ignored
+/// the pointer events of any other window are the ones of a popup grabbing the pointer
+fn bar_event(event: x::Event, bar: Window) -> Option<BarEvent> {
+    Some(match event {
+        x::Event::ButtonPress(e) if e.event() == bar => BarEvent::ButtonPress {
+            button: e.detail().into(),
+            x: e.event_x(),
+            y: e.event_y(),
+        },
+        x::Event::ButtonPress(e) => BarEvent::PopupButtonPress {
+            button: e.detail().into(),
+            x: e.event_x(),
+            y: e.event_y(),
+        },
+        x::Event::ButtonRelease(e) if e.event() == bar => BarEvent::ButtonRelease {
+            button: e.detail().into(),
+            x: e.event_x(),
+            y: e.event_y(),
+        },
+        x::Event::EnterNotify(e) if e.event() == bar => BarEvent::Motion {
+            x: e.event_x(),
+            y: e.event_y(),
+        },
+        x::Event::LeaveNotify(e) if e.event() == bar => BarEvent::Leave,
+        // the content of the window has been lost
+        x::Event::Expose(e) if e.count() == 0 && e.window() == bar => BarEvent::Redraw,
+        x::Event::Expose(e) if e.count() == 0 => BarEvent::PopupRedraw,
+        _ => return None,
+    })
+}
+
+/// Position of the pointer over `window`, querying it also lets the server
+/// send the next motion event of a window selecting `POINTER_MOTION_HINT`
+fn pointer_motion(connection: &Connection, window: Window) -> Option<BarEvent> {
+    let cookie = connection.send_request(&x::QueryPointer { window });
+    let reply = connection.wait_for_reply(cookie).ok()?;
+    reply.same_screen().then(|| BarEvent::Motion {
+        x: reply.win_x(),
+        y: reply.win_y(),
+    })
+}
+
+/// Keysyms of every keycode, used to translate the key presses
+struct Keymap {
+    min_keycode: u8,
+    keysyms_per_keycode: usize,
+    keysyms: Vec<u32>,
+}
+
+impl Keymap {
+    fn new(connection: &Connection) -> Result<Self> {
+        let setup = connection.get_setup();
+        let min_keycode = setup.min_keycode();
+        let cookie = connection.send_request(&x::GetKeyboardMapping {
+            first_keycode: min_keycode,
+            count: setup.max_keycode() - min_keycode + 1,
+        });
+        let reply = connection.wait_for_reply(cookie)?;
+        Ok(Self {
+            min_keycode,
+            keysyms_per_keycode: reply.keysyms_per_keycode().into(),
+            keysyms: reply.keysyms().to_vec(),
+        })
+    }
+
+    fn key(&self, keycode: u8, state: x::KeyButMask) -> Key {
+        let first =
+            usize::from(keycode.saturating_sub(self.min_keycode)) * self.keysyms_per_keycode;
+        let keysym = |column: usize| self.keysyms.get(first + column).copied().unwrap_or(0);
+        let shifted = state.contains(x::KeyButMask::SHIFT) && self.keysyms_per_keycode > 1;
+        match (shifted, keysym(1)) {
+            (true, 0) => match Key::from(keysym(0)) {
+                // the keycode has no shifted keysym, letters are made uppercase
+                Key::Char(c) => Key::Char(c.to_uppercase().next().unwrap_or(c)),
+                key => key,
+            },
+            (true, shifted) => shifted.into(),
+            (false, _) => keysym(0).into(),
+        }
+    }
+}
+
+fn bar_event_listener(connection: Arc<Connection>, bar: Window) -> Result<Receiver<BarEvent>> {
+    let (tx, rx) = bounded(10);
+    let mut keymap = Keymap::new(&connection)?;
+    thread::spawn(move || loop {
+        let event = match connection.wait_for_event() {
+            Ok(Event::X(x::Event::MotionNotify(e))) if e.event() == bar => {
+                pointer_motion(&connection, bar)
+            }
+            Ok(Event::X(x::Event::KeyPress(e))) => {
+                Some(BarEvent::KeyPress(keymap.key(e.detail(), e.state())))
+            }
+            Ok(Event::X(x::Event::MappingNotify(_))) => {
+                match Keymap::new(&connection) {
+                    Ok(new) => keymap = new,
+                    Err(e) => error!("Cannot read the keyboard mapping: {}", e),
+                }
+                None
+            }
+            Ok(Event::X(event)) => bar_event(event, bar),
+            Ok(Event::RandR(_)) => Some(BarEvent::ScreenChange),
+            _ => None,
+        };
+        let Some(event) = event else {
+            continue;
+        };
+        if tx.send_blocking(event).is_err() {
+            error!("bar_event_listener channel closed");
+            break;
+        }
+    });
+    Ok(rx)
+}
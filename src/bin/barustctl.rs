@@ -0,0 +1,66 @@
+//! Sends a command to the control socket of a running barust
+//!
+//! `barustctl [--socket <path>] <command> [args...]`, see [barust::ipc] for the commands
+
+use barust::ipc;
+use std::{
+    env,
+    io::{self, Read, Write},
+    net::Shutdown,
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    process::exit,
+};
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut socket = ipc::socket_path();
+    if args.first().map(String::as_str) == Some("--socket") {
+        if args.len() < 2 {
+            usage();
+        }
+        socket = Some(PathBuf::from(args.remove(1)));
+        args.remove(0);
+    }
+    if args.is_empty() {
+        usage();
+    }
+    let Some(socket) = socket else {
+        eprintln!("XDG_RUNTIME_DIR is not set, pass the path of the socket with --socket");
+        exit(1);
+    };
+
+    match send(&socket, &args.join(" ")) {
+        Ok(reply) => {
+            print!("{}", reply);
+            if reply.starts_with("error:") {
+                exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}: {}", socket.display(), e);
+            exit(1);
+        }
+    }
+}
+
+fn send(socket: &PathBuf, command: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(socket)?;
+    writeln!(stream, "{}", command)?;
+    stream.shutdown(Shutdown::Write)?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply)
+}
+
+fn usage() -> ! {
+    eprintln!("usage: barustctl [--socket <path>] <command> [args...]");
+    eprintln!();
+    eprintln!("commands:");
+    eprintln!("  update <name>           update a widget now");
+    eprintln!("  set-text <name> <text>  set the text of a text widget");
+    eprintln!("  show | hide             show or hide the bar");
+    eprintln!("  toggle <name>           show or hide a widget");
+    eprintln!("  list                    list the widgets and their width");
+    exit(2);
+}
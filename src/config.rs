@@ -0,0 +1,969 @@
+//! Builds a [StatusBar] from a TOML configuration file
+//!
+//! ```toml
+//! [bar]
+//! position = "top"
+//! height = 21
+//! background = "#000000ff"
+//!
+//! # defaults for every widget, each widget can override them
+//! [widget]
+//! font = "DejaVu Sans"
+//! font_size = 15.0
+//! fg_color = "#ffffff"
+//! # drawn by the bar around every widget
+//! background = "#285577"
+//! corner_radius = 4.0
+//! margin = 2
+//!
+//! [[left]]
+//! type = "workspaces"
+//!
+//! # colors picked from the value shown, green up to 50, yellow up to 80, then red
+//! [[right]]
+//! type = "cpu"
+//! fg_thresholds = [[0, "#00ff00"], [50, "#ffff00"], [80, "#ff0000"]]
+//!
+//! [[right]]
+//! type = "clock"
+//! format = "%H:%M"
+//! padding = 5
+//! ```
+
+use crate::{
+    statusbar::{Reloader, StatusBar, StatusBarBuilder},
+    utils::{Color, Position, Side, Style, Thresholds},
+    widgets::{
+        ActiveProvider, ActiveWindow, Apt, Battery, BatteryAction, BatterySelection, Brightness,
+        BrightnessctlProvider, Clock, Cpu, Disk, LightProvider, Memory, MeteoIcons, Microphone,
+        Network, NeverHide, OpenMeteoProvider, PowerSupply, PulseaudioProvider, ReplaceableWidget,
+        Spacer, SupervisionPolicy, SysfsProvider, Systray, Temperatures, Text, Threshold,
+        ThresholdWarner, Update, UpdateSource, Volume, Weather, Widget, WidgetConfig, Wlan,
+        Workspaces,
+    },
+    Result,
+};
+use futures_util::future::LocalBoxFuture;
+use serde::Deserialize;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env,
+    fmt::{self, Display},
+    future::Future,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+    time::Duration,
+};
+use toml::{Spanned, Value};
+
+type Table = BTreeMap<String, Spanned<Value>>;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    bar: Option<Spanned<Table>>,
+    widget: Option<Spanned<Table>>,
+    #[serde(default)]
+    left: Vec<Spanned<Table>>,
+    #[serde(default)]
+    center: Vec<Spanned<Table>>,
+    #[serde(default)]
+    right: Vec<Spanned<Table>>,
+}
+
+/// `$XDG_CONFIG_HOME/barust/config.toml`, or `~/.config/barust/config.toml`
+pub fn default_path() -> PathBuf {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let mut home = PathBuf::from(env::var_os("HOME").unwrap_or_default());
+            home.push(".config");
+            home
+        });
+    config_home.join("barust").join("config.toml")
+}
+
+/// Reads the configuration at `path` and creates the widgets using `registry`
+pub async fn load(path: impl AsRef<Path>, registry: &WidgetRegistry) -> Result<StatusBarBuilder> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|e| ConfigError {
+        file: path.to_owned(),
+        line: None,
+        key: None,
+        message: e.to_string(),
+    })?;
+    let file: ConfigFile = toml::from_str(&source).map_err(|e| ConfigError {
+        file: path.to_owned(),
+        line: e.span().map(|span| line_of(&source, span.start)),
+        key: None,
+        message: e.message().to_string(),
+    })?;
+
+    let mut defaults = WidgetConfig::default();
+    let mut default_policy = SupervisionPolicy::default();
+    if let Some(widget) = &file.widget {
+        let table = ConfigTable::new(path, &source, widget);
+        apply_widget_config(&mut defaults, &table)?;
+        apply_policy(&mut default_policy, &table)?;
+        table.check_unused()?;
+    }
+
+    // the tooltips and the placeholders look like the widgets
+    let mut builder = StatusBar::create()
+        .tooltip(defaults.clone())
+        .widget_config(defaults.clone());
+    if let Some(bar) = &file.bar {
+        let table = ConfigTable::new(path, &source, bar);
+        builder = apply_bar(builder, &table)?;
+        table.check_unused()?;
+    }
+
+    for (side, entries) in [
+        (Side::Left, &file.left),
+        (Side::Center, &file.center),
+        (Side::Right, &file.right),
+    ] {
+        for entry in entries {
+            let table = ConfigTable::new(path, &source, entry);
+            let name = table.string("name")?;
+            let mut policy = default_policy.clone();
+            apply_policy(&mut policy, &table)?;
+            let (widget, config) = registry.build(&table, &defaults).await?;
+            let widget = match name {
+                Some(name) => ReplaceableWidget::named(widget, name),
+                None => ReplaceableWidget::new(widget),
+            };
+            builder = builder.add_widget(side, widget.with_policy(policy).with_config(config));
+        }
+    }
+    Ok(builder)
+}
+
+/// Like [load], but the [StatusBar] is rebuilt whenever the file changes or SIGHUP is received
+pub async fn load_watched(
+    path: impl AsRef<Path>,
+    registry: WidgetRegistry,
+) -> Result<StatusBarBuilder> {
+    let path = path.as_ref().to_owned();
+    let registry = Rc::new(registry);
+    let builder = load(&path, &registry).await?;
+
+    let watched = path.clone();
+    let reloader: Reloader = Box::new(move || {
+        let path = path.clone();
+        let registry = Rc::clone(&registry);
+        Box::pin(async move { load(&path, &registry).await })
+    });
+    Ok(builder.reload_from(watched, reloader))
+}
+
+fn apply_bar(
+    mut builder: StatusBarBuilder,
+    table: &ConfigTable,
+) -> std::result::Result<StatusBarBuilder, ConfigError> {
+    if let Some(position) = table.parsed::<Position>("position")? {
+        builder = builder.position(position);
+    }
+    if let Some(height) = table.unsigned("height")? {
+        builder = builder.height(height);
+    }
+    if let Some(width) = table.unsigned("width")? {
+        builder = builder.width(width);
+    }
+    if let Some(xoff) = table.unsigned("xoff")? {
+        builder = builder.xoff(xoff);
+    }
+    if let Some(yoff) = table.unsigned("yoff")? {
+        builder = builder.yoff(yoff);
+    }
+    if let Some(output) = table.string("output")? {
+        builder = builder.output(output);
+    }
+    if let Some(socket) = table.string("socket")? {
+        builder = builder.socket(socket);
+    }
+    if let Some(background) = table.color("background")? {
+        builder = builder.background(background);
+    }
+    if let Some(false) = table.boolean("tooltips")? {
+        builder = builder.without_tooltips();
+    }
+    if let Some(delay) = table.duration("tooltip_delay")? {
+        builder = builder.tooltip_delay(delay);
+    }
+    Ok(builder)
+}
+
+fn apply_widget_config(
+    config: &mut WidgetConfig,
+    table: &ConfigTable,
+) -> std::result::Result<(), ConfigError> {
+    if let Some(font) = table.string("font")? {
+        config.font = font;
+    }
+    if let Some(font_size) = table.float("font_size")? {
+        config.font_size = font_size;
+    }
+    if let Some(padding) = table.unsigned("padding")? {
+        config.padding = padding;
+    }
+    if let Some(fg_color) = table.color("fg_color")? {
+        config.fg_color = fg_color;
+    }
+    if let Some(hide_timeout) = table.duration("hide_timeout")? {
+        config.hide_timeout = hide_timeout;
+    }
+    if let Some(osd) = table.boolean("osd")? {
+        config.osd = osd;
+    }
+    if let Some(flex) = table.boolean("flex")? {
+        config.flex = flex;
+    }
+    if let Some(update_interval) = table.duration("update_interval")? {
+        config.update_interval = update_interval;
+    }
+    if let Some(markup) = table.boolean("markup")? {
+        config.markup = markup;
+    }
+    for (key, thresholds) in [
+        ("fg_thresholds", &mut config.fg_thresholds),
+        ("bg_thresholds", &mut config.bg_thresholds),
+    ] {
+        if let Some(stops) = table.thresholds(key)? {
+            *thresholds = stops.gradient(thresholds.is_gradient());
+        }
+    }
+    if let Some(gradient) = table.boolean("gradient")? {
+        config.fg_thresholds = config.fg_thresholds.clone().gradient(gradient);
+        config.bg_thresholds = config.bg_thresholds.clone().gradient(gradient);
+    }
+    apply_style(&mut config.style, table)
+}
+
+fn apply_style(style: &mut Style, table: &ConfigTable) -> std::result::Result<(), ConfigError> {
+    if let Some(background) = table.color("background")? {
+        style.background = Some(background);
+    }
+    if let Some(underline) = table.color("underline")? {
+        style.underline = Some(underline);
+    }
+    if let Some(overline) = table.color("overline")? {
+        style.overline = Some(overline);
+    }
+    if let Some(accent_width) = table.unsigned("accent_width")? {
+        style.accent_width = accent_width;
+    }
+    if let Some(border) = table.color("border")? {
+        style.border = Some(border);
+    }
+    if let Some(border_width) = table.unsigned("border_width")? {
+        style.border_width = border_width;
+    }
+    if let Some(corner_radius) = table.float("corner_radius")? {
+        style.corner_radius = corner_radius;
+    }
+    if let Some(margin) = table.unsigned("margin")? {
+        style.margin = margin;
+    }
+    if let Some(separator) = table.parsed("separator")? {
+        style.separator = Some(separator);
+    }
+    Ok(())
+}
+
+fn apply_policy(
+    policy: &mut SupervisionPolicy,
+    table: &ConfigTable,
+) -> std::result::Result<(), ConfigError> {
+    if let Some(backoff) = table.duration("retry_backoff")? {
+        policy.initial_backoff = backoff;
+    }
+    if let Some(backoff) = table.duration("max_backoff")? {
+        policy.max_backoff = backoff;
+    }
+    if let Some(max_failures) = table.unsigned("max_failures")? {
+        policy.max_failures = max_failures;
+    }
+    if let Some(placeholder) = table.string("error_placeholder")? {
+        policy.placeholder = Some(placeholder);
+    }
+    Ok(())
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+/// A table of the configuration file, it keeps track of the keys that were read
+/// so that unknown keys can be reported
+pub struct ConfigTable<'a> {
+    file: &'a Path,
+    source: &'a str,
+    /// position in `source` of the text the spans are relative to
+    offset: usize,
+    /// path of a nested table, prepended to the keys in the errors
+    prefix: String,
+    span: Range<usize>,
+    table: &'a Table,
+    used: RefCell<BTreeSet<&'a str>>,
+}
+
+impl<'a> ConfigTable<'a> {
+    fn new(file: &'a Path, source: &'a str, table: &'a Spanned<Table>) -> Self {
+        Self {
+            file,
+            source,
+            offset: 0,
+            prefix: String::new(),
+            span: table.span(),
+            table: table.get_ref(),
+            used: RefCell::new(BTreeSet::new()),
+        }
+    }
+
+    /// The tables found at `key` by [ConfigTable::tables], their errors point at their own keys
+    fn nested<'b>(&'b self, key: &str, tables: &'b NestedTables) -> Vec<ConfigTable<'b>> {
+        tables
+            .tables
+            .iter()
+            .enumerate()
+            .map(|(index, table)| ConfigTable {
+                offset: tables.offset,
+                prefix: format!("{}{}[{}].", self.prefix, key, index),
+                ..ConfigTable::new(self.file, self.source, table)
+            })
+            .collect()
+    }
+
+    /// The tables of the array at `key`, parsed again from the source to keep their spans
+    fn tables(&self, key: &str) -> std::result::Result<Option<NestedTables>, ConfigError> {
+        if self.get(key).is_none() {
+            return Ok(None);
+        }
+        let span = self.table[key].span();
+        let start = self.offset + span.start;
+        let text = &self.source[start..self.offset + span.end];
+        #[derive(Deserialize)]
+        struct Inline {
+            array: Vec<Spanned<Table>>,
+        }
+        type Headers = BTreeMap<String, Vec<Spanned<Table>>>;
+        const PREFIX: &str = "array = ";
+        // `key = [{ .. }, { .. }]`
+        if let Ok(inline) = toml::from_str::<Inline>(&format!("{}{}", PREFIX, text)) {
+            return Ok(Some(NestedTables {
+                offset: start.saturating_sub(PREFIX.len()),
+                tables: inline.array,
+            }));
+        }
+        // `[[side.key]]` headers, the text starts with the first header
+        let headers: std::result::Result<BTreeMap<String, Headers>, _> = toml::from_str(text);
+        match headers.map(|sides| sides.into_values().flat_map(BTreeMap::into_values).next()) {
+            Ok(Some(tables)) => Ok(Some(NestedTables {
+                offset: start,
+                tables,
+            })),
+            _ => Err(self.error(key, "expected an array of tables")),
+        }
+    }
+
+    /// Creates an error pointing at `key`, or at the whole table if `key` is missing
+    pub fn error(&self, key: &str, message: impl Display) -> ConfigError {
+        let span = self
+            .table
+            .get(key)
+            .map(|value| value.span())
+            .unwrap_or_else(|| self.span.clone());
+        ConfigError {
+            file: self.file.to_owned(),
+            line: Some(line_of(self.source, self.offset + span.start)),
+            key: Some(format!("{}{}", self.prefix, key)),
+            message: message.to_string(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        let (key, value) = self.table.get_key_value(key)?;
+        self.used.borrow_mut().insert(key.as_str());
+        Some(value.get_ref())
+    }
+
+    pub fn string(&self, key: &str) -> std::result::Result<Option<String>, ConfigError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(self.error(key, "expected a string")),
+        }
+    }
+
+    pub fn strings(&self, key: &str) -> std::result::Result<Option<Vec<String>>, ConfigError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(|v| match v {
+                    Value::String(s) => Ok(s.clone()),
+                    _ => Err(self.error(key, "expected an array of strings")),
+                })
+                .collect::<std::result::Result<_, _>>()
+                .map(Some),
+            Some(_) => Err(self.error(key, "expected an array of strings")),
+        }
+    }
+
+    pub fn unsigned<T: TryFrom<i64>>(
+        &self,
+        key: &str,
+    ) -> std::result::Result<Option<T>, ConfigError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Integer(i)) => T::try_from(*i)
+                .map(Some)
+                .map_err(|_| self.error(key, "value out of range")),
+            Some(_) => Err(self.error(key, "expected an integer")),
+        }
+    }
+
+    pub fn float(&self, key: &str) -> std::result::Result<Option<f64>, ConfigError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Float(f)) => Ok(Some(*f)),
+            Some(Value::Integer(i)) => Ok(Some(*i as f64)),
+            Some(_) => Err(self.error(key, "expected a number")),
+        }
+    }
+
+    pub fn boolean(&self, key: &str) -> std::result::Result<Option<bool>, ConfigError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Boolean(b)) => Ok(Some(*b)),
+            Some(_) => Err(self.error(key, "expected a boolean")),
+        }
+    }
+
+    /// A duration expressed in seconds
+    pub fn duration(&self, key: &str) -> std::result::Result<Option<Duration>, ConfigError> {
+        match self.float(key)? {
+            None => Ok(None),
+            Some(secs) => Duration::try_from_secs_f64(secs)
+                .map(Some)
+                .map_err(|e| self.error(key, e)),
+        }
+    }
+
+    /// A string parsed with [FromStr]
+    pub fn parsed<T>(&self, key: &str) -> std::result::Result<Option<T>, ConfigError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.string(key)? {
+            None => Ok(None),
+            Some(s) => s.parse().map(Some).map_err(|e| self.error(key, e)),
+        }
+    }
+
+    /// A color in the `#rrggbb` or `#rrggbbaa` format
+    pub fn color(&self, key: &str) -> std::result::Result<Option<Color>, ConfigError> {
+        self.parsed(key)
+    }
+
+    /// Colors of the values starting at a number, e.g. `[[0, "#00ff00"], [80, "#ff0000"]]`
+    pub fn thresholds(&self, key: &str) -> std::result::Result<Option<Thresholds>, ConfigError> {
+        let expected = || self.error(key, "expected an array of [number, color] pairs");
+        let Some(value) = self.get(key) else {
+            return Ok(None);
+        };
+        let Value::Array(stops) = value else {
+            return Err(expected());
+        };
+        let mut thresholds = Thresholds::new();
+        for stop in stops {
+            let (value, color) = match stop.as_array().map(Vec::as_slice) {
+                Some([Value::Integer(i), Value::String(color)]) => (*i as f64, color),
+                Some([Value::Float(f), Value::String(color)]) => (*f, color),
+                _ => return Err(expected()),
+            };
+            let color = color.parse::<Color>().map_err(|e| self.error(key, e))?;
+            thresholds = thresholds.at(value, color);
+        }
+        Ok(Some(thresholds))
+    }
+
+    fn check_unused(&self) -> std::result::Result<(), ConfigError> {
+        let used = self.used.borrow();
+        match self.table.keys().find(|key| !used.contains(key.as_str())) {
+            Some(key) => Err(self.error(key, "unknown key")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Tables of an array in a [ConfigTable]
+struct NestedTables {
+    /// position of the parsed text in the source
+    offset: usize,
+    tables: Vec<Spanned<Table>>,
+}
+
+pub type WidgetFuture = LocalBoxFuture<'static, Result<Box<dyn Widget>>>;
+
+type Constructor =
+    Box<dyn Fn(&ConfigTable, WidgetConfig) -> std::result::Result<WidgetFuture, ConfigError>>;
+
+/// Maps the widget `type` used in the configuration to the widget constructor
+///
+/// A constructor reads its options from the [ConfigTable] and returns the future creating the widget
+pub struct WidgetRegistry {
+    constructors: HashMap<String, Constructor>,
+}
+
+impl fmt::Debug for WidgetRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.constructors.keys()).finish()
+    }
+}
+
+impl WidgetRegistry {
+    /// Creates a registry without any widget
+    pub fn empty() -> Self {
+        Self {
+            constructors: HashMap::new(),
+        }
+    }
+
+    /// Registers a widget `type`, replacing any previous one with the same name
+    pub fn register<F>(&mut self, name: impl ToString, constructor: F)
+    where
+        F: Fn(&ConfigTable, WidgetConfig) -> std::result::Result<WidgetFuture, ConfigError>
+            + 'static,
+    {
+        self.constructors
+            .insert(name.to_string(), Box::new(constructor));
+    }
+
+    async fn build(
+        &self,
+        table: &ConfigTable<'_>,
+        defaults: &WidgetConfig,
+    ) -> Result<(Box<dyn Widget>, WidgetConfig)> {
+        let kind = table
+            .string("type")?
+            .ok_or_else(|| table.error("type", "missing widget type"))?;
+        let constructor = self
+            .constructors
+            .get(&kind)
+            .ok_or_else(|| table.error("type", format!("unknown widget type {}", kind)))?;
+        let mut config = defaults.clone();
+        apply_widget_config(&mut config, table)?;
+        let widget = constructor(table, config.clone())?;
+        table.check_unused()?;
+        Ok((widget.await?, config))
+    }
+}
+
+/// Wraps a widget constructor into a [WidgetFuture]
+pub fn widget<W, F>(f: F) -> std::result::Result<WidgetFuture, ConfigError>
+where
+    W: Widget + 'static,
+    F: Future<Output = Result<Box<W>>> + 'static,
+{
+    Ok(Box::pin(async move { Ok(f.await? as Box<dyn Widget>) }))
+}
+
+/// Thresholds of the battery warnings, e.g.
+/// `warnings = [{ below = 5, urgency = "critical", repeat = 60, action = "suspend" }]`,
+/// `action` is `suspend`, `hibernate` or a shell command
+fn battery_thresholds(
+    table: &ConfigTable,
+) -> std::result::Result<Option<Vec<Threshold>>, ConfigError> {
+    const KEY: &str = "warnings";
+    let Some(entries) = table.tables(KEY)? else {
+        return Ok(None);
+    };
+    let mut thresholds = Vec::new();
+    for entry in table.nested(KEY, &entries) {
+        let below = entry
+            .float("below")?
+            .ok_or_else(|| entry.error("below", "missing below"))?;
+        let mut threshold = Threshold::new(below);
+        if let Some(urgency) = entry.parsed("urgency")? {
+            threshold = threshold.urgency(urgency);
+        }
+        if let Some(message) = entry.string("message")? {
+            threshold = threshold.message(&message);
+        }
+        if let Some(repeat) = entry.duration("repeat")? {
+            threshold = threshold.repeat(repeat);
+        }
+        if let Some(action) = entry.string("action")? {
+            threshold = threshold.action(match action.as_str() {
+                "suspend" => BatteryAction::Suspend,
+                "hibernate" => BatteryAction::Hibernate,
+                _ => BatteryAction::Command(action),
+            });
+        }
+        entry.check_unused()?;
+        thresholds.push(threshold);
+    }
+    Ok(Some(thresholds))
+}
+
+impl Default for WidgetRegistry {
+    /// A registry with all the widgets provided by barust
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register("active_window", |_, config| {
+            widget(async move { Ok(ActiveWindow::new(&config).await?) })
+        });
+        registry.register("battery", |table, config| {
+            let format = table
+                .string("format")?
+                .unwrap_or(Battery::DEFAULT_FORMAT.into());
+            // all the batteries are combined by default
+            let power_supply = match table.string("battery")? {
+                Some(name) => PowerSupply::new(BatterySelection::Named(name)),
+                None => PowerSupply::default(),
+            };
+            let notify = table.boolean("notify")?.unwrap_or(true);
+            let warner = match battery_thresholds(table)? {
+                _ if !notify => ThresholdWarner::new(),
+                Some(thresholds) => thresholds
+                    .into_iter()
+                    .fold(ThresholdWarner::new(), ThresholdWarner::threshold),
+                None => ThresholdWarner::default(),
+            };
+            let charger = table.boolean("charger_notifications")?.unwrap_or(false);
+            let warner = warner.charger_notifications(charger);
+            widget(async move {
+                let battery = Battery::new(format, power_supply, None, &config, warner);
+                Ok(battery.await?)
+            })
+        });
+        registry.register("brightness", |table, config| {
+            let format = table
+                .string("format")?
+                .unwrap_or(Brightness::DEFAULT_FORMAT.into());
+            let provider = table.string("provider")?;
+            match provider.as_deref() {
+                None | Some("sysfs") => widget(async move {
+                    let provider = Box::new(SysfsProvider::new().await?);
+                    Ok(Brightness::new(format, provider, None, &config).await)
+                }),
+                Some("light") => widget(async move {
+                    let provider = Box::new(LightProvider::new());
+                    Ok(Brightness::new(format, provider, None, &config).await)
+                }),
+                Some("brightnessctl") => {
+                    let provider = match table.string("device")? {
+                        Some(device) => BrightnessctlProvider::with_device(device),
+                        None => BrightnessctlProvider::new(),
+                    };
+                    widget(async move {
+                        Ok(Brightness::new(format, Box::new(provider), None, &config).await)
+                    })
+                }
+                Some(other) => Err(table.error(
+                    "provider",
+                    format!(
+                        "unknown provider {}, expected sysfs, light or brightnessctl",
+                        other
+                    ),
+                )),
+            }
+        });
+        registry.register("clock", |table, config| {
+            let format = table.string("format")?.unwrap_or("%H:%M".into());
+            widget(async move { Ok(Clock::new(format, &config).await) })
+        });
+        registry.register("cpu", |table, config| {
+            let format = table
+                .string("format")?
+                .unwrap_or(Cpu::DEFAULT_FORMAT.into());
+            widget(async move { Ok(Cpu::new(format, &config).await?) })
+        });
+        registry.register("disk", |table, config| {
+            let format = table
+                .string("format")?
+                .unwrap_or(Disk::DEFAULT_FORMAT.into());
+            let path = table.string("path")?.unwrap_or("/".into());
+            widget(async move { Ok(Disk::new(format, path, &config).await) })
+        });
+        registry.register("memory", |table, config| {
+            let format = table
+                .string("format")?
+                .unwrap_or(Memory::DEFAULT_FORMAT.into());
+            widget(async move { Ok(Memory::new(format, &config).await) })
+        });
+        registry.register("microphone", |table, config| {
+            let format = table
+                .string("format")?
+                .unwrap_or(Volume::DEFAULT_FORMAT.into());
+            widget(async move {
+                let provider = Box::new(PulseaudioProvider::source().await?);
+                Ok(Microphone::new(format, provider, None, &config).await)
+            })
+        });
+        registry.register("network", |table, config| {
+            let format = table
+                .string("format")?
+                .unwrap_or(Network::DEFAULT_FORMAT.into());
+            let interface = table
+                .string("interface")?
+                .ok_or_else(|| table.error("interface", "missing network interface"))?;
+            widget(async move { Ok(Network::new(format, interface, None, &config).await) })
+        });
+        registry.register("spacer", |table, _| {
+            let size = table
+                .unsigned("size")?
+                .ok_or_else(|| table.error("size", "missing spacer size"))?;
+            widget(async move { Ok(Spacer::new(size).await) })
+        });
+        registry.register("systray", |table, config| {
+            let internal_padding = table.unsigned("internal_padding")?.unwrap_or(10);
+            widget(async move { Ok(Systray::new(internal_padding, &config).await?) })
+        });
+        registry.register("temperatures", |table, config| {
+            let format = table
+                .string("format")?
+                .unwrap_or(Temperatures::DEFAULT_FORMAT.into());
+            widget(async move { Ok(Temperatures::new(format, &config).await) })
+        });
+        registry.register("text", |table, config| {
+            let text = table
+                .string("text")?
+                .ok_or_else(|| table.error("text", "missing text"))?;
+            widget(async move { Ok(Text::new(text, &config).await) })
+        });
+        registry.register("update", |table, config| {
+            let sources = table
+                .strings("sources")?
+                .unwrap_or_else(|| vec!["apt".into()])
+                .into_iter()
+                .map(|source| match source.as_str() {
+                    "apt" => Ok(Apt::new() as Box<dyn UpdateSource>),
+                    other => Err(table.error(
+                        "sources",
+                        format!("unknown update source {}, expected apt", other),
+                    )),
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            widget(async move { Ok(Update::new(&config, sources).await) })
+        });
+        registry.register("volume", |table, config| {
+            let format = table
+                .string("format")?
+                .unwrap_or(Volume::DEFAULT_FORMAT.into());
+            widget(async move {
+                let provider = Box::new(PulseaudioProvider::new().await?);
+                Ok(Volume::new(format, provider, None, &config).await)
+            })
+        });
+        registry.register("weather", |table, config| {
+            let format = table
+                .string("format")?
+                .unwrap_or(Weather::DEFAULT_FORMAT.into());
+            widget(async move {
+                Ok(Weather::new(
+                    &format,
+                    MeteoIcons::default(),
+                    &config,
+                    OpenMeteoProvider::new(),
+                )
+                .await)
+            })
+        });
+        registry.register("wlan", |table, config| {
+            let format = table
+                .string("format")?
+                .unwrap_or(Wlan::DEFAULT_FORMAT.into());
+            let interface = table
+                .string("interface")?
+                .ok_or_else(|| table.error("interface", "missing network interface"))?;
+            widget(async move { Ok(Wlan::new(format, interface, &config).await) })
+        });
+        registry.register("workspaces", |table, config| {
+            let active_color = table.color("active_color")?.unwrap_or(config.fg_color);
+            let internal_padding = table.unsigned("internal_padding")?.unwrap_or(10);
+            widget(async move {
+                Ok(Workspaces::new(
+                    active_color,
+                    internal_padding,
+                    &config,
+                    NeverHide,
+                    ActiveProvider::new()?,
+                )
+                .await)
+            })
+        });
+        registry
+    }
+}
+
+/// An error in the configuration file
+#[derive(Debug)]
+pub struct ConfigError {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub key: Option<String>,
+    pub message: String,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(key) = &self.key {
+            write!(f, ": {}", key)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BarustError;
+    use std::fs;
+
+    async fn load_str(source: &str) -> Result<StatusBarBuilder> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, source).unwrap();
+        load(&path, &WidgetRegistry::default()).await
+    }
+
+    async fn config_error(source: &str) -> ConfigError {
+        match load_str(source).await {
+            Err(BarustError::Config(e)) => e,
+            Err(e) => panic!("expected a configuration error, got {}", e),
+            Ok(_) => panic!("expected a configuration error"),
+        }
+    }
+
+    /// The battery warnings of the first widget on the right
+    fn warnings(source: &str) -> std::result::Result<Option<Vec<Threshold>>, ConfigError> {
+        let file: ConfigFile = toml::from_str(source).unwrap();
+        let table = ConfigTable::new(Path::new("config.toml"), source, &file.right[0]);
+        battery_thresholds(&table)
+    }
+
+    #[tokio::test]
+    async fn valid_file() {
+        let source = r##"
+[bar]
+height = 21
+background = "#000000ff"
+
+[widget]
+font_size = 12
+margin = 2
+
+[[left]]
+type = "text"
+text = "hello"
+name = "greeting"
+
+[[right]]
+type = "spacer"
+size = 10
+"##;
+        assert!(load_str(source).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn unknown_key() {
+        let e = config_error("[[left]]\ntype = \"text\"\ntext = \"hello\"\ncolour = \"#ffffff\"\n")
+            .await;
+        assert_eq!(e.line, Some(4));
+        assert_eq!(e.key.as_deref(), Some("colour"));
+        assert_eq!(e.message, "unknown key");
+    }
+
+    #[tokio::test]
+    async fn wrong_type() {
+        let e = config_error("[widget]\nfont = \"Sans\"\nfont_size = \"big\"\n").await;
+        assert_eq!(e.line, Some(3));
+        assert_eq!(e.key.as_deref(), Some("font_size"));
+        assert_eq!(e.message, "expected a number");
+    }
+
+    #[tokio::test]
+    async fn unknown_widget() {
+        let e =
+            config_error("[[left]]\ntype = \"text\"\ntext = \"a\"\n\n[[right]]\ntype = \"nope\"\n")
+                .await;
+        assert_eq!(e.line, Some(6));
+        assert_eq!(e.key.as_deref(), Some("type"));
+        assert_eq!(e.message, "unknown widget type nope");
+    }
+
+    #[test]
+    fn battery_warnings() {
+        let inline = r#"
+[[right]]
+type = "battery"
+warnings = [
+    { below = 10, urgency = "normal", message = "Low" },
+    { below = 5, repeat = 60, action = "suspend" },
+]
+"#;
+        assert_eq!(warnings(inline).unwrap().unwrap().len(), 2);
+
+        let headers = r#"
+[[right]]
+type = "battery"
+
+[[right.warnings]]
+below = 10
+
+[[right.warnings]]
+below = 5
+action = "hibernate"
+"#;
+        assert_eq!(warnings(headers).unwrap().unwrap().len(), 2);
+        assert!(warnings("[[right]]\ntype = \"battery\"\n")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn battery_warnings_errors_point_at_the_entry() {
+        let e = warnings(
+            "[[right]]\nwarnings = [\n    { below = 10 },\n    { below = \"five\" },\n]\n",
+        )
+        .unwrap_err();
+        assert_eq!(e.line, Some(4));
+        assert_eq!(e.key.as_deref(), Some("warnings[1].below"));
+        assert_eq!(e.message, "expected a number");
+
+        let e = warnings(
+            "[[right]]\ntype = \"battery\"\n\n[[right.warnings]]\nbelow = 10\n\n[[right.warnings]]\nbelow = 5\nurgency = \"urgent\"\n",
+        )
+        .unwrap_err();
+        assert_eq!(e.line, Some(9));
+        assert_eq!(e.key.as_deref(), Some("warnings[1].urgency"));
+
+        let e = warnings("[[right]]\nwarnings = [\n    { below = 10, repeats = 60 },\n]\n")
+            .unwrap_err();
+        assert_eq!(e.line, Some(3));
+        assert_eq!(e.key.as_deref(), Some("warnings[0].repeats"));
+        assert_eq!(e.message, "unknown key");
+
+        let e = warnings("[[right]]\nwarnings = [\n    {},\n    { urgency = \"low\" },\n]\n")
+            .unwrap_err();
+        assert_eq!(e.line, Some(3));
+        assert_eq!(e.key.as_deref(), Some("warnings[0].below"));
+
+        let e = warnings("[[right]]\nwarnings = 5\n").unwrap_err();
+        assert_eq!(e.line, Some(2));
+        assert_eq!(e.key.as_deref(), Some("warnings"));
+    }
+}
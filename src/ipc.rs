@@ -0,0 +1,179 @@
+//! Control socket of a running [StatusBar](crate::statusbar::StatusBar)
+//!
+//! Every line sent to the socket is a command, the bar answers with `ok`,
+//! `error: <message>` or the requested output, then closes the connection
+//! when the client stops writing.
+//!
+//! * `update <name>` updates the widgets called `name` right away
+//! * `set-text <name> <text>` replaces the content of the [Text](crate::widgets::Text) widgets called `name`
+//! * `show`, `hide` maps or unmaps the bar
+//! * `toggle <name>` hides or shows the widgets called `name`
+//! * `list` prints every widget with its section and width
+
+use async_channel::{bounded, Receiver, Sender};
+use log::{debug, warn};
+use std::{
+    env, fmt,
+    fs::{self, Permissions},
+    io,
+    os::unix::{fs::PermissionsExt, net::UnixStream},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream as AsyncUnixStream},
+    spawn,
+    sync::oneshot,
+};
+
+/// `$XDG_RUNTIME_DIR/barust.sock`, `None` if the runtime directory is not set
+///
+/// A shared directory like `/tmp` is not used instead, other users could take the path.
+pub fn socket_path() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| PathBuf::from(dir).join("barust.sock"))
+}
+
+/// A command accepted by the control socket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Update(String),
+    SetText(String, String),
+    Show,
+    Hide,
+    Toggle(String),
+    List,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut words = s.trim().splitn(3, ' ');
+        let command = words.next().unwrap_or_default();
+        let mut name = || {
+            words
+                .next()
+                .filter(|name| !name.is_empty())
+                .map(ToString::to_string)
+                .ok_or_else(|| format!("{} needs a widget name", command))
+        };
+        Ok(match command {
+            "update" => Self::Update(name()?),
+            "set-text" => {
+                let name = name()?;
+                Self::SetText(name, words.next().unwrap_or_default().to_string())
+            }
+            "show" => Self::Show,
+            "hide" => Self::Hide,
+            "toggle" => Self::Toggle(name()?),
+            "list" => Self::List,
+            "" => return Err(String::from("empty command")),
+            other => return Err(format!("unknown command {}", other)),
+        })
+    }
+}
+
+/// Answer of the bar to a [Command]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    Ok,
+    Output(String),
+    Error(String),
+}
+
+impl fmt::Display for Reply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::Output(output) => write!(f, "{}", output),
+            Self::Error(message) => write!(f, "error: {}", message),
+        }
+    }
+}
+
+/// A [Command] waiting for the bar to answer
+#[derive(Debug)]
+pub(crate) struct Request {
+    pub command: Command,
+    reply: oneshot::Sender<Reply>,
+}
+
+impl Request {
+    pub fn reply(self, reply: Reply) {
+        self.reply.send(reply).ok();
+    }
+}
+
+/// Starts serving the socket at `path`, returns `None` if another bar
+/// is already listening on it or the socket cannot be created
+pub(crate) fn listen(path: PathBuf) -> Option<Receiver<Request>> {
+    if path.exists() && UnixStream::connect(&path).is_ok() {
+        warn!(
+            "{:?} is used by another bar, the control socket is disabled",
+            path
+        );
+        return None;
+    }
+    let listener = match bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!(
+                "Cannot listen on {:?}, the control socket is disabled: {}",
+                path, e
+            );
+            return None;
+        }
+    };
+    debug!("Listening on {:?}", path);
+
+    let (tx, rx) = bounded(10);
+    spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    spawn(serve(stream, tx.clone()));
+                }
+                Err(e) => {
+                    warn!("Stopped listening on {:?}: {}", path, e);
+                    break;
+                }
+            }
+        }
+    });
+    Some(rx)
+}
+
+/// Creates the socket at `path`, only reachable by the current user
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    if path.exists() {
+        // left behind by a bar that didn't shut down cleanly
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+async fn serve(stream: AsyncUnixStream, requests: Sender<Request>) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let reply = match line.parse() {
+            Ok(command) => {
+                let (reply, answer) = oneshot::channel();
+                if requests.send(Request { command, reply }).await.is_err() {
+                    break;
+                }
+                answer
+                    .await
+                    .unwrap_or_else(|_| Reply::Error(String::from("the bar is shutting down")))
+            }
+            Err(e) => Reply::Error(e),
+        };
+        writer.write_all(format!("{}\n", reply).as_bytes()).await?;
+    }
+    Ok(())
+}
@@ -1,3 +1,6 @@
+pub mod backend;
+pub mod config;
+pub mod ipc;
 pub mod statusbar;
 pub mod utils;
 pub mod widgets;
@@ -7,9 +10,14 @@ use thiserror::Error;
 #[error(transparent)]
 pub enum BarustError {
     Cairo(#[from] cairo::Error),
+    Config(#[from] config::ConfigError),
     #[error("Draw was called without any regions defined")]
     DrawBeforeUpdate,
     Io(#[from] std::io::Error),
+    #[error("Output {0} not found")]
+    OutputNotFound(String),
+    #[error("{0} is not supported by this backend")]
+    Unsupported(&'static str),
     Widget(#[from] widgets::WidgetError),
     Xcb(#[from] xcb::Error),
 }
@@ -0,0 +1,41 @@
+use barust::{
+    config::{self, WidgetRegistry},
+    Result,
+};
+use log::LevelFilter;
+use std::{env, path::PathBuf, process::exit};
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run().await {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+async fn run() -> Result<()> {
+    let mut level = LevelFilter::Warn;
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        level = match arg.as_str() {
+            "--trace" => LevelFilter::Trace,
+            "--debug" => LevelFilter::Debug,
+            "--info" => LevelFilter::Info,
+            "--warn" => LevelFilter::Warn,
+            "--error" => LevelFilter::Error,
+            _ => {
+                path = Some(PathBuf::from(arg));
+                continue;
+            }
+        }
+    }
+    simple_logging::log_to_stderr(level);
+
+    let path = path.unwrap_or_else(config::default_path);
+    config::load_watched(&path, WidgetRegistry::default())
+        .await?
+        .build()
+        .await?
+        .start()
+        .await
+}
@@ -1,40 +1,136 @@
 use crate::utils::{
-    hook_sender::RightLeft, screen_true_height, screen_true_width, set_source_rgba, Atoms, Color,
-    HookSender, Position, Rectangle, ResettableTimer, StatusBarInfo, TimedHooks, WidgetID,
+    hook_sender::Side, set_source_rgba, Color, HookSender, Key, Monitor, MouseButton, Position,
+    Rectangle, StatusBarInfo, TimedHooks, WidgetID,
 };
 use crate::{
-    widgets::{ReplaceableWidget, Size, Widget},
+    backend::{Backend, BarEvent, ImageBackend, Placement, PopupWindow, Tooltip, XcbBackend},
+    ipc::{self, Command, Reply},
+    widgets::{ReplaceableWidget, Size, SupervisionPolicy, Systray, Widget, WidgetConfig},
     BarustError, Result,
 };
 use async_channel::{bounded, Receiver};
-use cairo::{Context, Operator, XCBConnection, XCBDrawable, XCBSurface, XCBVisualType};
-use futures_util::stream::StreamExt;
-use log::{debug, error};
-use signal_hook::consts::signal::{SIGINT, SIGTERM};
+use cairo::{Context, ImageSurface, Operator};
+use futures_util::{future::LocalBoxFuture, stream::StreamExt};
+use inotify::{Inotify, WatchMask};
+use log::{debug, error, info, warn};
+use signal_hook::consts::signal::{SIGHUP, SIGINT, SIGTERM};
 use signal_hook_tokio::Signals;
-use std::{ffi::c_int, sync::Arc, thread, time::Duration};
-use tokio::{select, spawn};
-use xcb::{
-    x::{
-        Colormap, ColormapAlloc, CreateColormap, CreateWindow, Cw, EventMask, MapWindow, Pixmap,
-        VisualClass, Visualtype, Window, WindowClass,
-    },
-    Connection, Event, Xid,
+use std::{
+    ffi::c_int,
+    fs,
+    future::Future,
+    mem,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+use tokio::{
+    select, spawn,
+    task::JoinHandle,
+    time::{sleep_until, Instant},
 };
-
 /// Represents the Bar displayed on the screen
 pub struct StatusBar {
     background: Color,
-    connection: Arc<Connection>,
+    backend: Box<dyn Backend>,
     left_regions: Vec<Rectangle>,
     left_widgets: Vec<ReplaceableWidget>,
+    center_regions: Vec<Rectangle>,
+    center_widgets: Vec<ReplaceableWidget>,
     right_regions: Vec<Rectangle>,
     right_widgets: Vec<ReplaceableWidget>,
-    surface: XCBSurface,
     height: u32,
     width: u32,
-    window: Window,
-    position: Position,
+    placement: Placement,
+    reload: Option<Reload>,
+    socket: Option<PathBuf>,
+    hidden: bool,
+    /// copy of the window content, only the damaged parts are painted again
+    back_buffer: Option<ImageSurface>,
+    /// regions of the widgets when the bar was last drawn
+    drawn_regions: Vec<Rectangle>,
+    full_redraw: bool,
+    /// font, colors and style of the tooltips, `None` if they are disabled
+    tooltip_config: Option<WidgetConfig>,
+    tooltip_delay: Duration,
+    tooltip: Option<Tooltip>,
+    /// widget under the pointer
+    hovered: Option<WidgetID>,
+    /// when the tooltip of the hovered widget is shown
+    tooltip_due: Option<Instant>,
+    popup: Option<OpenPopup>,
+}
+
+/// What the bar does with a [Popup] after one of its events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopupAction {
+    /// draws the popup again
+    Keep,
+    Close,
+}
+
+/// Content of a window opened under a widget by [Widget::popup]
+///
+/// The window grabs the pointer and the keyboard while it is open,
+/// a click outside of it or [Key::Escape] closes it.
+pub trait Popup: std::fmt::Debug + Send {
+    /// Width and height of the content
+    fn size(&self, context: &Context) -> crate::widgets::Result<(u32, u32)>;
+    /// Draws the content on the whole window, `width` and `height` are the ones of [Popup::size]
+    fn draw(&self, context: &Context, width: u32, height: u32) -> crate::widgets::Result<()>;
+    /// Called when a button is pressed over the popup, `x` and `y` are relative to it
+    fn on_click(
+        &mut self,
+        _button: MouseButton,
+        _x: u32,
+        _y: u32,
+    ) -> crate::widgets::Result<PopupAction> {
+        Ok(PopupAction::Keep)
+    }
+    fn on_key(&mut self, _key: Key) -> crate::widgets::Result<PopupAction> {
+        Ok(PopupAction::Keep)
+    }
+}
+
+/// A [Popup] shown in its window
+#[derive(Debug)]
+struct OpenPopup {
+    /// the widget that opened the popup, updated once it is closed
+    owner: WidgetID,
+    content: Box<dyn Popup>,
+    window: PopupWindow,
+}
+
+impl OpenPopup {
+    fn paint(&mut self) -> Result<()> {
+        let (width, height) = self.content.size(&Context::new(self.window.surface())?)?;
+        self.window.show(width, height)?;
+        let context = Context::new(self.window.surface())?;
+        context.set_operator(Operator::Source);
+        set_source_rgba(&context, Color::new(0.0, 0.0, 0.0, 0.0));
+        context.paint()?;
+        context.set_operator(Operator::Over);
+        self.content.draw(&context, width, height)?;
+        self.window.flush()
+    }
+
+    fn contains(&self, x: i16, y: i16) -> bool {
+        let Some((width, height)) = self.window.size() else {
+            return false;
+        };
+        match (u32::try_from(x), u32::try_from(y)) {
+            (Ok(x), Ok(y)) => x < width && y < height,
+            _ => false,
+        }
+    }
+}
+
+/// Creates the [StatusBarBuilder] used to rebuild a running [StatusBar]
+pub type Reloader = Box<dyn Fn() -> LocalBoxFuture<'static, Result<StatusBarBuilder>>>;
+
+struct Reload {
+    path: PathBuf,
+    reloader: Reloader,
 }
 
 impl StatusBar {
@@ -44,63 +140,87 @@ impl StatusBar {
         StatusBarBuilder::default()
     }
 
+    /// Creates one [StatusBar] for every active monitor,
+    /// `f` is called once per monitor and returns the builder with the widgets for that monitor
+    pub async fn per_monitor<F, Fut>(mut f: F) -> Result<Vec<StatusBar>>
+    where
+        F: FnMut(Monitor) -> Fut,
+        Fut: Future<Output = Result<StatusBarBuilder>>,
+    {
+        let mut bars = Vec::new();
+        for monitor in Monitor::all()? {
+            let output = monitor.name.clone();
+            bars.push(f(monitor).await?.output(output).build().await?);
+        }
+        Ok(bars)
+    }
+
+    /// Runs the loop of multiple [StatusBar]s at the same time
+    pub async fn start_all(bars: Vec<StatusBar>) -> Result<()> {
+        futures_util::future::try_join_all(bars.into_iter().map(StatusBar::start)).await?;
+        Ok(())
+    }
+
     /// Starts the [StatusBar] drawing and event loop
     pub async fn start(mut self) -> Result<()> {
         debug!("Starting loop");
-        let (tx, widgets_events) = bounded::<WidgetID>(10);
-
-        debug!("Widget setup");
-        let info = StatusBarInfo {
-            background: self.background,
-            left_regions: self.left_regions.clone(),
-            right_regions: self.right_regions.clone(),
-            height: self.height,
-            width: self.width,
-            position: self.position,
-            window: self.window,
-        };
-        let mut pool = TimedHooks::default();
-        for (index, wd) in self.left_widgets.iter_mut().enumerate() {
-            wd.setup_or_replace(&info).await;
-            wd.hook_or_replace(
-                HookSender::new(tx.clone(), (RightLeft::Left, index)),
-                &mut pool,
-            )
-            .await;
-        }
-        for (index, wd) in self.right_widgets.iter_mut().enumerate() {
-            wd.setup_or_replace(&info).await;
-            wd.hook_or_replace(
-                HookSender::new(tx.clone(), (RightLeft::Right, index)),
-                &mut pool,
-            )
-            .await;
-        }
-        for wd in self.left_widgets.iter_mut() {
-            wd.update_or_replace().await;
-        }
-        for wd in self.right_widgets.iter_mut() {
-            wd.update_or_replace().await;
-        }
+        let (mut widgets_events, mut timed_hooks) = self.start_widgets().await;
 
         let signal = notify(&[SIGINT, SIGTERM])?;
-        let bar_events = bar_event_listener(Arc::clone(&self.connection))?;
+        let bar_events = self.backend.events()?;
+        let bar_events_enabled = bar_events.is_some();
+        let bar_events = bar_events.unwrap_or_else(|| bounded(1).1);
+        let reload_events = match &self.reload {
+            Some(reload) => reload_listener(&reload.path)?,
+            None => bounded(1).1,
+        };
+        let ipc_requests = match &self.socket {
+            Some(path) => ipc::listen(path.clone()),
+            None => None,
+        };
+        let ipc_enabled = ipc_requests.is_some();
+        let ipc_requests = ipc_requests.unwrap_or_else(|| bounded(1).1);
 
         self.generate_regions().await?;
         self.draw().await?;
         self.show()?;
-        pool.start().await;
+        self.tooltip = self.create_tooltip();
 
-        let mut draw_timer = ResettableTimer::new(Duration::from_millis(1000 / 60));
         loop {
             let mut to_update: Option<WidgetID> = None;
+            let tooltip_due = self.tooltip_due;
             select!(
                 id = widgets_events.recv() => {
                     to_update = id.ok();
                 }
-                _ = bar_events.recv() => {/* just redraw? */ }
+                Ok(event) = bar_events.recv(), if bar_events_enabled => {
+                    self.handle_bar_event(event).await?;
+                }
+                _ = sleep_until(tooltip_due.unwrap_or_else(Instant::now)), if tooltip_due.is_some() => {
+                    self.show_tooltip();
+                }
+                Ok(request) = ipc_requests.recv(), if ipc_enabled => {
+                    let reply = self.handle_command(request.command.clone()).await;
+                    request.reply(reply);
+                }
+                Ok(()) = reload_events.recv(), if self.reload.is_some() => {
+                    match self.reload().await {
+                        Ok(Some(reloaded)) => {
+                            for task in mem::replace(&mut timed_hooks, reloaded.1) {
+                                task.abort();
+                            }
+                            // the hooks of the old widgets stop when their channel is closed
+                            widgets_events = reloaded.0;
+                        }
+                        Ok(None) => {}
+                        Err(e) => error!("Failed to reload the configuration: {}", e),
+                    }
+                }
                 _ = signal.recv() => {
                     // shutdown
+                    if let Some(path) = self.socket.as_ref().filter(|_| ipc_enabled) {
+                        fs::remove_file(path).ok();
+                    }
                     return Ok(())
                 },
             );
@@ -113,135 +233,615 @@ impl StatusBar {
                 self.update(to_update).await?;
             }
 
-            self.generate_regions().await?;
-
-            if draw_timer.is_done() {
-                draw_timer.reset();
+            // motions and tooltips don't change the bar
+            if self.full_redraw || self.widgets_mut().any(|wd| wd.is_dirty()) {
+                self.generate_regions().await?;
                 self.draw().await?;
             }
         }
     }
 
-    async fn update(&mut self, (side, index): WidgetID) -> Result<()> {
-        let wd = match side {
-            RightLeft::Left => &mut self.left_widgets[index],
-            RightLeft::Right => &mut self.right_widgets[index],
+    /// Sets up and hooks every widget,
+    /// returns the channel of the update requests and the tasks of the timed hooks
+    async fn start_widgets(&mut self) -> (Receiver<WidgetID>, Vec<JoinHandle<()>>) {
+        let (tx, widgets_events) = bounded::<WidgetID>(10);
+
+        self.setup_widgets().await;
+        let mut pool = TimedHooks::default();
+        for (side, widgets) in [
+            (Side::Left, &mut self.left_widgets),
+            (Side::Center, &mut self.center_widgets),
+            (Side::Right, &mut self.right_widgets),
+        ] {
+            for (index, wd) in widgets.iter_mut().enumerate() {
+                wd.hook_or_replace(HookSender::new(tx.clone(), (side, index)), &mut pool)
+                    .await;
+            }
+        }
+        for wd in self.widgets_mut() {
+            wd.update_or_replace().await;
+        }
+        (widgets_events, pool.start().await)
+    }
+
+    async fn setup_widgets(&mut self) {
+        debug!("Widget setup");
+        let info = StatusBarInfo {
+            background: self.background,
+            left_regions: self.left_regions.clone(),
+            center_regions: self.center_regions.clone(),
+            right_regions: self.right_regions.clone(),
+            height: self.height,
+            width: self.width,
+            position: self.placement.position,
+            window: self.backend.window(),
+            connection: self.backend.connection(),
+            monitor: self.backend.monitor(),
+        };
+        for wd in self.widgets_mut() {
+            wd.setup_or_replace(&info).await;
+        }
+    }
+
+    /// Updates every widget once and draws the bar,
+    /// used to render a bar created with [StatusBarBuilder::build_headless]
+    pub async fn render(&mut self) -> Result<()> {
+        for wd in self.widgets_mut() {
+            wd.update_or_replace().await;
+        }
+        self.generate_regions().await?;
+        self.draw().await
+    }
+
+    /// Writes the last drawn frame to a PNG file, if the backend supports it
+    pub fn write_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.backend.write_png(path.as_ref())
+    }
+
+    /// Replaces the widgets and the geometry with the ones of a new [StatusBarBuilder],
+    /// the window, the X connection and the systray are kept
+    async fn reload(&mut self) -> Result<Option<(Receiver<WidgetID>, Vec<JoinHandle<()>>)>> {
+        let Some(reload) = &self.reload else {
+            return Ok(None);
+        };
+        let builder = (reload.reloader)().await?;
+        info!("Reloading configuration from {:?}", reload.path);
+
+        let placement = builder.placement();
+        // a failure leaves the running widgets untouched
+        let geometry = match self.hidden {
+            true => None,
+            false => Some(self.backend.place(&placement)?),
+        };
+        let builder = builder.apply_policy();
+        // the widgets may have moved or been replaced
+        self.hover(None);
+        self.popup = None;
+
+        // the tray icons are adopted by the systray, recreating it would drop them
+        let mut systray = [
+            mem::take(&mut self.left_widgets),
+            mem::take(&mut self.center_widgets),
+            mem::take(&mut self.right_widgets),
+        ]
+        .into_iter()
+        .flatten()
+        .find(ReplaceableWidget::is::<Systray>)
+        .map(ReplaceableWidget::into_inner);
+        let mut keep_systray = |mut widgets: Vec<ReplaceableWidget>| {
+            for wd in &mut widgets {
+                if wd.is::<Systray>() {
+                    if let Some(old) = systray.take() {
+                        wd.replace_widget(old);
+                    }
+                }
+            }
+            widgets
+        };
+        self.left_widgets = keep_systray(builder.left_widgets);
+        self.center_widgets = keep_systray(builder.center_widgets);
+        self.right_widgets = keep_systray(builder.right_widgets);
+
+        self.background = builder.background;
+        self.tooltip_config = builder.tooltip_config;
+        self.tooltip_delay = builder.tooltip_delay;
+        self.placement = placement;
+        self.full_redraw = true;
+        if let Some(geometry) = geometry {
+            if let Err(e) = self.apply_geometry(geometry) {
+                warn!("Cannot show the reloaded bar: {}", e);
+            }
+        }
+
+        Ok(Some(self.start_widgets().await))
+    }
+
+    async fn update(&mut self, id: WidgetID) -> Result<()> {
+        let Some(wd) = self.widget_mut(id) else {
+            // sent by a widget replaced by a reload
+            return Ok(());
         };
         wd.update_or_replace().await;
+        if self.hovered == Some(id) && self.tooltip.as_ref().is_some_and(Tooltip::is_visible) {
+            // keep the tooltip in sync with the widget
+            self.show_tooltip();
+        }
         Ok(())
     }
 
-    async fn generate_regions(&mut self) -> Result<()> {
-        let context = Context::new(&self.surface)?;
-        let mut rectangle = Rectangle {
-            x: 0,
-            y: 0,
-            width: 0,
-            height: self.height,
+    fn widget_mut(&mut self, (side, index): WidgetID) -> Option<&mut ReplaceableWidget> {
+        match side {
+            Side::Left => self.left_widgets.get_mut(index),
+            Side::Center => self.center_widgets.get_mut(index),
+            Side::Right => self.right_widgets.get_mut(index),
+        }
+    }
+
+    async fn handle_command(&mut self, command: Command) -> Reply {
+        debug!("Received command {:?}", command);
+        let named = |name: &str| {
+            let name = name.to_owned();
+            move |wd: &&mut ReplaceableWidget| wd.name() == name
         };
+        match command {
+            Command::Update(name) => {
+                let mut found = false;
+                for wd in self.widgets_mut().filter(named(&name)) {
+                    wd.update_or_replace().await;
+                    found = true;
+                }
+                if !found {
+                    return Reply::Error(format!("no widget named {}", name));
+                }
+            }
+            Command::SetText(name, text) => {
+                let mut found = false;
+                for wd in self.widgets_mut().filter(named(&name)) {
+                    if let Some(inner) = wd.as_text_mut() {
+                        if inner.set_text(&text) {
+                            wd.mark_dirty();
+                        }
+                        found = true;
+                    }
+                }
+                if !found {
+                    return Reply::Error(format!("no text widget named {}", name));
+                }
+            }
+            Command::Toggle(name) => {
+                let mut found = false;
+                for wd in self.widgets_mut().filter(named(&name)) {
+                    wd.set_hidden(!wd.is_hidden());
+                    found = true;
+                }
+                if !found {
+                    return Reply::Error(format!("no widget named {}", name));
+                }
+            }
+            Command::Show => {
+                self.hidden = false;
+                if let Err(e) = self.reposition() {
+                    return Reply::Error(e.to_string());
+                }
+            }
+            Command::Hide => {
+                self.hidden = true;
+                self.hover(None);
+                if let Err(e) = self.hide() {
+                    return Reply::Error(e.to_string());
+                }
+            }
+            Command::List => {
+                let sections = [
+                    ("left", &self.left_widgets, &self.left_regions),
+                    ("center", &self.center_widgets, &self.center_regions),
+                    ("right", &self.right_widgets, &self.right_regions),
+                ];
+                let lines: Vec<String> = sections
+                    .into_iter()
+                    .flat_map(|(side, widgets, regions)| {
+                        widgets.iter().zip(regions).map(move |(wd, region)| {
+                            let mut line = format!("{}\t{}\t{}", side, wd.name(), region.width);
+                            if wd.is_hidden() {
+                                line.push_str(" (hidden)");
+                            }
+                            if let Some(e) = wd.last_error() {
+                                line.push_str(&format!(" (error: {})", e));
+                            }
+                            line
+                        })
+                    })
+                    .collect();
+                return Reply::Output(lines.join("\n"));
+            }
+        }
+        Reply::Ok
+    }
 
-        let static_size: u32 = self
-            .left_widgets
-            .iter_mut()
-            .chain(&mut self.right_widgets)
-            .map(|wd| {
-                if let Ok(Size::Static(width)) = wd.size(&context) {
-                    width
-                } else {
-                    0
+    async fn handle_bar_event(&mut self, event: BarEvent) -> Result<()> {
+        match event {
+            BarEvent::ButtonPress { button, x, y } => {
+                // the tooltip comes back once the pointer moves to another widget
+                self.hide_tooltip();
+                if self.popup.is_some() {
+                    // the pointer is not grabbed, the click closes the popup anyway
+                    return self.close_popup().await;
                 }
-            })
-            .sum();
+                let Some(((side, index), region)) = self.widget_id_at(x, y) else {
+                    return Ok(());
+                };
+                let Some((wd, x, y)) = self.widget_at(x, y) else {
+                    return Ok(());
+                };
+                debug!("{} clicked with {:?}", wd, button);
+                if let Some(content) = wd.popup_or_replace(button).await {
+                    self.open_popup((side, index), region, content);
+                    return Ok(());
+                }
+                wd.on_click_or_replace(button, x, y).await;
+                wd.update_or_replace().await;
+            }
+            BarEvent::PopupButtonPress { button, x, y } => {
+                let Some(popup) = &mut self.popup else {
+                    return Ok(());
+                };
+                if !popup.contains(x, y) {
+                    return self.close_popup().await;
+                }
+                let action = popup.content.on_click(button, x as u32, y as u32);
+                self.handle_popup_action(action).await?;
+            }
+            BarEvent::KeyPress(key) => {
+                let Some(popup) = &mut self.popup else {
+                    return Ok(());
+                };
+                let action = match key {
+                    Key::Escape => Ok(PopupAction::Close),
+                    key => popup.content.on_key(key),
+                };
+                self.handle_popup_action(action).await?;
+            }
+            BarEvent::ButtonRelease { button, x, y } => {
+                if let Some((wd, x, y)) = self.widget_at(x, y) {
+                    wd.on_release_or_replace(button, x, y).await;
+                    wd.update_or_replace().await;
+                }
+            }
+            BarEvent::Motion { x, y } if self.popup.is_none() => {
+                let hovered = self.widget_id_at(x, y).map(|(id, _)| id);
+                self.hover(hovered);
+            }
+            BarEvent::Motion { .. } => {}
+            BarEvent::Leave => self.hover(None),
+            BarEvent::ScreenChange => self.reposition()?,
+            BarEvent::Redraw => self.full_redraw = true,
+            BarEvent::PopupRedraw => self.paint_popup().await?,
+        }
+        Ok(())
+    }
 
-        let flex_widgets = self
-            .left_widgets
+    /// Shows `content` under `region`, the region of the widget `owner`
+    fn open_popup(&mut self, owner: WidgetID, region: Rectangle, content: Box<dyn Popup>) {
+        let (Some(connection), Some(monitor)) = (self.backend.connection(), self.backend.monitor())
+        else {
+            debug!("The backend cannot show popups");
+            return;
+        };
+        self.hover(None);
+        let window = match PopupWindow::new(connection, self.backend.window(), monitor, region) {
+            Ok(window) => window,
+            Err(e) => {
+                warn!("Cannot create the popup window: {}", e);
+                return;
+            }
+        };
+        let mut popup = OpenPopup {
+            owner,
+            content,
+            window,
+        };
+        match popup.paint() {
+            Ok(()) => self.popup = Some(popup),
+            Err(e) => warn!("Cannot show the popup: {}", e),
+        }
+    }
+
+    async fn handle_popup_action(
+        &mut self,
+        action: crate::widgets::Result<PopupAction>,
+    ) -> Result<()> {
+        match action {
+            Ok(PopupAction::Keep) => self.paint_popup().await,
+            Ok(PopupAction::Close) => self.close_popup().await,
+            Err(e) => {
+                warn!("Popup failed: {}", e);
+                self.close_popup().await
+            }
+        }
+    }
+
+    async fn paint_popup(&mut self) -> Result<()> {
+        let Some(popup) = &mut self.popup else {
+            return Ok(());
+        };
+        if let Err(e) = popup.paint() {
+            warn!("Cannot draw the popup: {}", e);
+            return self.close_popup().await;
+        }
+        Ok(())
+    }
+
+    /// Closes the popup and updates the widget that opened it,
+    /// its content may have changed the state of the widget
+    async fn close_popup(&mut self) -> Result<()> {
+        if let Some(popup) = self.popup.take() {
+            let owner = popup.owner;
+            drop(popup);
+            self.update(owner).await?;
+        }
+        Ok(())
+    }
+
+    /// The tooltip window on the monitor of the bar, if the backend can show one
+    fn create_tooltip(&self) -> Option<Tooltip> {
+        let config = self.tooltip_config.as_ref()?;
+        let connection = self.backend.connection()?;
+        let monitor = self.backend.monitor()?;
+        Tooltip::new(connection, self.backend.window(), monitor, config)
+            .map_err(|e| warn!("Cannot create the tooltip window: {}", e))
+            .ok()
+    }
+
+    /// Starts the delay of the tooltip when the pointer moves to another widget
+    fn hover(&mut self, hovered: Option<WidgetID>) {
+        if self.hovered == hovered {
+            return;
+        }
+        self.hide_tooltip();
+        self.hovered = hovered;
+        self.tooltip_due = hovered
+            .filter(|_| self.tooltip.is_some())
+            .map(|_| Instant::now() + self.tooltip_delay);
+    }
+
+    /// Shows the tooltip of the hovered widget, if it has one
+    fn show_tooltip(&mut self) {
+        self.tooltip_due = None;
+        let Some((side, index)) = self.hovered else {
+            return;
+        };
+        let region = match side {
+            Side::Left => self.left_regions.get(index),
+            Side::Center => self.center_regions.get(index),
+            Side::Right => self.right_regions.get(index),
+        }
+        .copied();
+        let text = self.widget_mut((side, index)).and_then(|wd| wd.tooltip());
+        let (Some(tooltip), Some(region)) = (&mut self.tooltip, region) else {
+            return;
+        };
+        let result = match text {
+            Some(text) => tooltip.show(&text, &region),
+            None => tooltip.hide(),
+        };
+        if let Err(e) = result {
+            warn!("Cannot show the tooltip: {}", e);
+        }
+    }
+
+    fn hide_tooltip(&mut self) {
+        self.tooltip_due = None;
+        if let Some(Err(e)) = self.tooltip.as_mut().map(Tooltip::hide) {
+            warn!("Cannot hide the tooltip: {}", e);
+        }
+    }
+
+    /// Moves and resizes the bar to follow its monitor after a RandR change
+    fn reposition(&mut self) -> Result<()> {
+        // the widgets may have moved or been replaced
+        self.hover(None);
+        self.popup = None;
+        if self.hidden {
+            return Ok(());
+        }
+        let geometry = self.backend.place(&self.placement)?;
+        self.apply_geometry(geometry)
+    }
+
+    /// Shows the bar with the geometry chosen by the backend,
+    /// hides it if its output is not available
+    fn apply_geometry(&mut self, geometry: Option<Rectangle>) -> Result<()> {
+        let Some(geometry) = geometry else {
+            warn!(
+                "Output {:?} is not available, hiding bar",
+                self.placement.output
+            );
+            return self.backend.hide();
+        };
+        self.width = geometry.width;
+        self.height = geometry.height;
+        self.full_redraw = true;
+        // the monitor, the font or the colors of the tooltip may have changed
+        self.tooltip = self.create_tooltip();
+        self.show()
+    }
+
+    /// Finds the widget under the point (`x`, `y`) and translates the point
+    /// into the part of its region left by its [Style](crate::utils::Style)
+    fn widget_at(&mut self, x: i16, y: i16) -> Option<(&mut ReplaceableWidget, u32, u32)> {
+        let ((side, index), region) = self.widget_id_at(x, y)?;
+        let wd = self.widget_mut((side, index))?;
+        let block = wd.style().block(&region, side);
+        let (x, y) = (x as u32, y as u32);
+        Some((wd, x.saturating_sub(block.x), y.saturating_sub(block.y)))
+    }
+
+    /// Finds the widget under the point (`x`, `y`) and its region
+    fn widget_id_at(&self, x: i16, y: i16) -> Option<(WidgetID, Rectangle)> {
+        let (x, y) = (u32::try_from(x).ok()?, u32::try_from(y).ok()?);
+        [
+            (Side::Left, &self.left_widgets, &self.left_regions),
+            (Side::Center, &self.center_widgets, &self.center_regions),
+            (Side::Right, &self.right_widgets, &self.right_regions),
+        ]
+        .into_iter()
+        .find_map(|(side, widgets, regions)| {
+            regions
+                .iter()
+                .take(widgets.len())
+                .position(|region| region.contains(x, y))
+                .map(|index| ((side, index), regions[index]))
+        })
+    }
+
+    fn widgets_mut(&mut self) -> impl Iterator<Item = &mut ReplaceableWidget> {
+        self.left_widgets
             .iter_mut()
+            .chain(&mut self.center_widgets)
             .chain(&mut self.right_widgets)
-            .flat_map(|wd| wd.size(&context))
-            .filter(|wd| wd.is_flex())
-            .count();
+    }
 
-        let flex_size = (self.width - static_size) / flex_widgets as u32;
+    async fn generate_regions(&mut self) -> Result<()> {
+        let context = Context::new(self.backend.surface())?;
 
-        self.left_regions.clear();
-        for wd in &mut self.left_widgets {
-            let widget_width = wd.size_or_replace(&context).await.unwrap_or(flex_size);
-            rectangle.width = widget_width;
-            self.left_regions.push(rectangle);
-            rectangle.x += widget_width;
+        let mut sizes: [Vec<Option<u32>>; 3] = Default::default();
+        for (widgets, sizes) in [
+            &mut self.left_widgets,
+            &mut self.center_widgets,
+            &mut self.right_widgets,
+        ]
+        .into_iter()
+        .zip(&mut sizes)
+        {
+            for wd in widgets {
+                sizes.push(match wd.size_or_replace(&context).await {
+                    Size::Flex => None,
+                    Size::Static(0) => Some(0),
+                    Size::Static(width) => Some(width + wd.style().extra_width(self.height)),
+                });
+            }
         }
 
-        self.right_regions.clear();
-        for wd in &mut self.right_widgets {
-            let widget_width = wd.size_or_replace(&context).await.unwrap_or(flex_size);
-            rectangle.width = widget_width;
-            self.right_regions.push(rectangle);
-            rectangle.x += widget_width;
-        }
+        [self.left_regions, self.center_regions, self.right_regions] =
+            layout(self.width, self.height, &sizes);
         Ok(())
     }
 
+    /// Paints the widgets that changed since the last call and the regions they left
     async fn draw(&mut self) -> Result<()> {
         if self.left_regions.len() != self.left_widgets.len()
+            || self.center_regions.len() != self.center_widgets.len()
             || self.right_regions.len() != self.right_widgets.len()
         {
             return Err(BarustError::DrawBeforeUpdate);
         }
 
-        let widgets = self
-            .left_widgets
-            .iter_mut()
-            .chain(self.right_widgets.iter_mut());
-
-        let regions: Vec<&Rectangle> = self
+        let regions: Vec<Rectangle> = self
             .left_regions
             .iter()
+            .chain(self.center_regions.iter())
             .chain(self.right_regions.iter())
+            .copied()
             .collect();
 
-        // double buffer to prevent flickering
-        let tmp_surface = self.surface.create_similar_image(
-            cairo::Format::ARgb32,
-            self.width as _,
-            self.height as _,
-        )?;
+        let back_buffer = match self.back_buffer.take() {
+            Some(surface)
+                if !self.full_redraw
+                    && surface.width() == self.width as i32
+                    && surface.height() == self.height as i32
+                    && regions.len() == self.drawn_regions.len() =>
+            {
+                surface
+            }
+            _ => {
+                self.full_redraw = true;
+                ImageSurface::create(cairo::Format::ARgb32, self.width as _, self.height as _)?
+            }
+        };
 
-        let contexts: Vec<_> = regions
-            .iter()
-            .map(|region| -> Result<Context> {
-                let cairo_rectangle: cairo::Rectangle = (**region).into();
-                let surface = &tmp_surface.create_for_rectangle(cairo_rectangle)?;
-                Ok(Context::new(surface)?)
-            })
-            .collect();
+        let dirty: Vec<bool> = self.widgets_mut().map(|wd| wd.take_dirty()).collect();
+        let mut damage = Vec::new();
+        for ((region, old), dirty) in regions.iter().zip(&self.drawn_regions).zip(dirty) {
+            if dirty || region != old {
+                damage.extend([*old, *region]);
+            }
+        }
+        if self.full_redraw {
+            damage = vec![Rectangle {
+                x: 0,
+                y: 0,
+                width: self.width,
+                height: self.height,
+            }];
+        }
+        damage.retain(|rectangle| !rectangle.is_empty());
+        self.drawn_regions = regions;
+        self.full_redraw = false;
 
-        for ((wd, rectangle), context) in widgets.zip(regions).zip(contexts) {
-            wd.draw_or_replace(&context?, rectangle).await;
+        if damage.is_empty() {
+            self.back_buffer = Some(back_buffer);
+            return Ok(());
         }
-        tmp_surface.flush();
 
-        let context = Context::new(&self.surface)?;
-        // clear surface
-        context.set_operator(Operator::Clear);
-        context.paint()?;
-        // paint background
-        context.set_operator(Operator::Over);
+        let clip = |context: &Context, dx: u32, dy: u32| {
+            for rectangle in &damage {
+                context.rectangle(
+                    f64::from(rectangle.x) - f64::from(dx),
+                    f64::from(rectangle.y) - f64::from(dy),
+                    rectangle.width.into(),
+                    rectangle.height.into(),
+                );
+            }
+            context.clip();
+        };
+
+        // the back buffer prevents flickering and keeps the parts that didn't change
+        let context = Context::new(&back_buffer)?;
+        clip(&context, 0, 0);
+        context.set_operator(Operator::Source);
         set_source_rgba(&context, self.background);
         context.paint()?;
-        // copy tmp_surface
-        context.set_source_surface(&tmp_surface, 0.0, 0.0)?;
-        context.paint()?;
-        self.surface.flush();
+        context.set_operator(Operator::Over);
 
-        self.connection.flush()?;
-        Ok(())
+        let widgets = self
+            .left_widgets
+            .iter_mut()
+            .map(|wd| (Side::Left, wd))
+            .chain(self.center_widgets.iter_mut().map(|wd| (Side::Center, wd)))
+            .chain(self.right_widgets.iter_mut().map(|wd| (Side::Right, wd)));
+        for ((side, wd), region) in widgets.zip(&self.drawn_regions) {
+            if region.is_empty() || !damage.iter().any(|d| d.intersects(region)) {
+                // collapsed because it didn't fit, or not damaged
+                continue;
+            }
+            let style = wd.style();
+            style.draw(&context, region, side)?;
+            let block = style.block(region, side);
+            if block.is_empty() {
+                continue;
+            }
+            let surface = back_buffer.create_for_rectangle(block.into())?;
+            let widget_context = Context::new(&surface)?;
+            clip(&widget_context, block.x, block.y);
+            wd.draw_or_replace(&widget_context, &block).await;
+        }
+        back_buffer.flush();
+
+        let context = Context::new(self.backend.surface())?;
+        clip(&context, 0, 0);
+        context.set_operator(Operator::Source);
+        context.set_source_surface(&back_buffer, 0.0, 0.0)?;
+        context.paint()?;
+        self.back_buffer = Some(back_buffer);
+        self.backend.flush()
     }
 
     fn show(&self) -> Result<()> {
-        self.connection.send_and_check_request(&MapWindow {
-            window: self.window,
-        })?;
-        Ok(())
+        self.backend.show()
+    }
+
+    fn hide(&self) -> Result<()> {
+        self.backend.hide()
     }
 }
 
@@ -253,8 +853,16 @@ pub struct StatusBarBuilder {
     height: u16,
     position: Position,
     background: Color,
-    left_widgets: Vec<Box<dyn Widget>>,
-    right_widgets: Vec<Box<dyn Widget>>,
+    left_widgets: Vec<ReplaceableWidget>,
+    center_widgets: Vec<ReplaceableWidget>,
+    right_widgets: Vec<ReplaceableWidget>,
+    output: Option<String>,
+    reload: Option<Reload>,
+    socket: Option<PathBuf>,
+    policy: SupervisionPolicy,
+    widget_config: WidgetConfig,
+    tooltip_config: Option<WidgetConfig>,
+    tooltip_delay: Duration,
 }
 
 impl Default for StatusBarBuilder {
@@ -267,7 +875,15 @@ impl Default for StatusBarBuilder {
             position: Position::Top,
             background: Color::new(0.0, 0.0, 0.0, 1.0),
             left_widgets: Vec::new(),
+            center_widgets: Vec::new(),
             right_widgets: Vec::new(),
+            output: None,
+            reload: None,
+            socket: ipc::socket_path(),
+            policy: SupervisionPolicy::default(),
+            widget_config: WidgetConfig::default(),
+            tooltip_config: Some(WidgetConfig::default()),
+            tooltip_delay: Duration::from_millis(500),
         }
     }
 }
@@ -279,7 +895,7 @@ impl StatusBarBuilder {
         self
     }
 
-    ///Set the `StatusBar` offset on the y axis
+    ///Set the `StatusBar` offset on the y axis, measured from the bottom edge when the bar is at the bottom
     pub fn yoff(mut self, offset: u16) -> Self {
         self.yoff = offset;
         self
@@ -303,6 +919,12 @@ impl StatusBarBuilder {
         self
     }
 
+    ///Set the RandR output (e.g. `DP-1`) where the `StatusBar` is displayed, defaults to the primary one
+    pub fn output(mut self, output: impl ToString) -> Self {
+        self.output = Some(output.to_string());
+        self
+    }
+
     ///Set the `StatusBar` background color
     pub fn background(mut self, background: Color) -> Self {
         self.background = background;
@@ -311,143 +933,297 @@ impl StatusBarBuilder {
 
     ///Add a widget to the `StatusBar` on the left
     pub fn left_widget(mut self, widget: Box<dyn Widget>) -> Self {
-        self.left_widgets.push(widget);
+        self.left_widgets.push(ReplaceableWidget::new(widget));
         self
     }
 
     ///Add multiple widgets to the `StatusBar` on the left
     pub fn left_widgets(mut self, widgets: Vec<Box<dyn Widget>>) -> Self {
         for wd in widgets {
-            self.left_widgets.push(wd);
+            self.left_widgets.push(ReplaceableWidget::new(wd));
+        }
+        self
+    }
+
+    ///Add a widget to the `StatusBar` in the center
+    pub fn center_widget(mut self, widget: Box<dyn Widget>) -> Self {
+        self.center_widgets.push(ReplaceableWidget::new(widget));
+        self
+    }
+
+    ///Add multiple widgets to the `StatusBar` in the center
+    pub fn center_widgets(mut self, widgets: Vec<Box<dyn Widget>>) -> Self {
+        for wd in widgets {
+            self.center_widgets.push(ReplaceableWidget::new(wd));
         }
         self
     }
 
     ///Add a widget to the `StatusBar` on the right
     pub fn right_widget(mut self, widget: Box<dyn Widget>) -> Self {
-        self.right_widgets.push(widget);
+        self.right_widgets.push(ReplaceableWidget::new(widget));
         self
     }
 
     ///Add multiple widgets to the `StatusBar` on the right
     pub fn right_widgets(mut self, widgets: Vec<Box<dyn Widget>>) -> Self {
         for wd in widgets {
-            self.right_widgets.push(wd);
+            self.right_widgets.push(ReplaceableWidget::new(wd));
         }
         self
     }
 
-    ///Build the `StatusBar` with the previously selected options
-    pub async fn build(self) -> Result<StatusBar> {
-        let (connection, screen_id) = Connection::connect(None)?;
-        let connection = Arc::new(connection);
+    ///Add a widget to the `side` section of the `StatusBar`,
+    ///`name` is used to refer to it through the control socket
+    pub fn named_widget(self, side: Side, name: impl ToString, widget: Box<dyn Widget>) -> Self {
+        self.add_widget(side, ReplaceableWidget::named(widget, name))
+    }
 
-        let width = self
-            .width
-            .unwrap_or_else(|| screen_true_width(&connection, screen_id));
+    ///Add an already wrapped widget to the `side` section of the `StatusBar`
+    pub fn add_widget(mut self, side: Side, widget: ReplaceableWidget) -> Self {
+        match side {
+            Side::Left => self.left_widgets.push(widget),
+            Side::Center => self.center_widgets.push(widget),
+            Side::Right => self.right_widgets.push(widget),
+        }
+        self
+    }
 
-        let window: Window = connection.generate_id();
-        let colormap: Colormap = connection.generate_id();
+    ///Set how failing widgets are retried and replaced,
+    ///widgets with their own [SupervisionPolicy] are not affected
+    pub fn supervision(mut self, policy: SupervisionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
 
-        let screen = connection
-            .get_setup()
-            .roots()
-            .nth(screen_id as _)
-            .unwrap_or_else(|| panic!("cannot find screen:{}", screen_id));
+    ///Set the font, colors and style of the placeholders of failing widgets,
+    ///widgets given their own with [ReplaceableWidget::with_config] are not affected
+    pub fn widget_config(mut self, config: WidgetConfig) -> Self {
+        self.widget_config = config;
+        self
+    }
 
-        let depth = screen
-            .allowed_depths()
-            .find(|d| d.depth() == 32)
-            .expect("cannot find valid depth");
+    ///Set the path of the control socket, see [ipc](crate::ipc)
+    pub fn socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.socket = Some(path.into());
+        self
+    }
 
-        let mut visual_type = depth
-            .visuals()
-            .iter()
-            .find(|v| v.class() == VisualClass::TrueColor)
-            .expect("cannot find valid visual type")
-            .to_owned();
-
-        connection.send_and_check_request(&CreateColormap {
-            alloc: ColormapAlloc::None,
-            mid: colormap,
-            window: screen.root(),
-            visual: visual_type.visual_id(),
-        })?;
-
-        connection.send_and_check_request(&CreateWindow {
-            depth: depth.depth(),
-            wid: window,
-            parent: screen.root(),
-            x: self.xoff as _,
-            y: match self.position {
-                Position::Top => self.yoff,
-                Position::Bottom => screen_true_height(&connection, screen_id) - self.height,
-            } as _,
-            width,
-            height: self.height,
-            border_width: 0,
-            class: WindowClass::InputOutput,
-            visual: visual_type.visual_id(),
-            value_list: &[
-                Cw::BackPixmap(Pixmap::none()),
-                Cw::BorderPixel(screen.black_pixel()),
-                Cw::EventMask(EventMask::all()),
-                Cw::Colormap(colormap),
-            ],
-        })?;
-
-        let atoms = Atoms::new(&connection)?;
-        connection.send_and_check_request(&xcb::x::ChangeProperty {
-            mode: xcb::x::PropMode::Replace,
-            window,
-            property: atoms._NET_WM_WINDOW_TYPE,
-            r#type: xcb::x::ATOM_ATOM,
-            data: &[atoms._NET_WM_WINDOW_TYPE_DOCK],
-        })?;
-
-        let surface = unsafe {
-            let conn_ptr = connection.get_raw_conn() as _;
-            XCBSurface::create(
-                &XCBConnection::from_raw_none(conn_ptr),
-                &XCBDrawable(window.resource_id()),
-                &XCBVisualType::from_raw_none(&mut visual_type as *mut Visualtype as _),
-                i32::from(width),
-                i32::from(self.height),
-            )?
-        };
+    ///Disable the control socket
+    pub fn without_socket(mut self) -> Self {
+        self.socket = None;
+        self
+    }
 
-        connection.flush()?;
+    ///Set the font, the colors and the [Style](crate::utils::Style) of the tooltips
+    pub fn tooltip(mut self, config: WidgetConfig) -> Self {
+        self.tooltip_config = Some(config);
+        self
+    }
+
+    ///Set how long the pointer stays over a widget before its tooltip is shown
+    pub fn tooltip_delay(mut self, delay: Duration) -> Self {
+        self.tooltip_delay = delay;
+        self
+    }
+
+    ///Never show the tooltips of the widgets
+    pub fn without_tooltips(mut self) -> Self {
+        self.tooltip_config = None;
+        self
+    }
+
+    ///Rebuild the widgets and the geometry of the running `StatusBar` with `reloader`
+    ///whenever the file at `path` changes or SIGHUP is received
+    pub fn reload_from(mut self, path: impl Into<PathBuf>, reloader: Reloader) -> Self {
+        self.reload = Some(Reload {
+            path: path.into(),
+            reloader,
+        });
+        self
+    }
+
+    ///Build the `StatusBar` with the previously selected options
+    pub async fn build(self) -> Result<StatusBar> {
+        self.build_with(Box::new(XcbBackend::new()?)).await
+    }
+
+    ///Build a `StatusBar` that is rendered to an image instead of a window,
+    ///the widgets are set up right away, see [StatusBar::render]
+    pub async fn build_headless(self) -> Result<StatusBar> {
+        let backend = ImageBackend::new(self.width.unwrap_or(1920), self.height)?;
+        let mut bar = self.build_with(Box::new(backend)).await?;
+        bar.setup_widgets().await;
+        Ok(bar)
+    }
+
+    ///Build the `StatusBar` on a custom [Backend]
+    pub async fn build_with(self, mut backend: Box<dyn Backend>) -> Result<StatusBar> {
+        let placement = self.placement();
+        let this = self.apply_policy();
+        let geometry = backend
+            .place(&placement)?
+            .ok_or_else(|| BarustError::OutputNotFound(this.output.clone().unwrap_or_default()))?;
 
         Ok(StatusBar {
-            background: self.background,
-            connection,
-            height: u32::from(self.height),
+            background: this.background,
+            backend,
+            height: geometry.height,
             left_regions: Vec::new(),
-            left_widgets: self
-                .left_widgets
-                .into_iter()
-                .map(ReplaceableWidget::new)
-                .collect(),
+            left_widgets: this.left_widgets,
+            center_regions: Vec::new(),
+            center_widgets: this.center_widgets,
             right_regions: Vec::new(),
-            right_widgets: self
-                .right_widgets
-                .into_iter()
-                .map(ReplaceableWidget::new)
-                .collect(),
-            surface,
-            width: u32::from(width),
-            window,
-            position: self.position,
+            right_widgets: this.right_widgets,
+            width: geometry.width,
+            placement,
+            reload: this.reload,
+            socket: this.socket,
+            hidden: false,
+            back_buffer: None,
+            drawn_regions: Vec::new(),
+            full_redraw: true,
+            tooltip_config: this.tooltip_config,
+            tooltip_delay: this.tooltip_delay,
+            tooltip: None,
+            hovered: None,
+            tooltip_due: None,
+            popup: None,
         })
     }
+
+    fn apply_policy(mut self) -> Self {
+        for wd in self
+            .left_widgets
+            .iter_mut()
+            .chain(&mut self.center_widgets)
+            .chain(&mut self.right_widgets)
+        {
+            wd.default_policy(&self.policy, &self.widget_config);
+        }
+        self
+    }
+
+    fn placement(&self) -> Placement {
+        Placement {
+            position: self.position,
+            xoff: self.xoff,
+            yoff: self.yoff,
+            width: self.width,
+            height: self.height,
+            output: self.output.clone(),
+        }
+    }
+}
+
+/// Lays out the left, center and right sections of the bar,
+/// `sizes` contains the width of every widget or `None` for flex widgets.
+///
+/// The left section starts at the left edge, the right one ends at the right edge
+/// and the center one stays in the middle as long as it fits between the other two.
+/// Widgets that don't fit are clipped, down to a width of zero.
+fn layout(width: u32, height: u32, sizes: &[Vec<Option<u32>>; 3]) -> [Vec<Rectangle>; 3] {
+    let [left, center, right] = sizes;
+    let static_width = |sizes: &[Option<u32>]| -> u32 { sizes.iter().flatten().sum() };
+    let flex_count = |sizes: &[Option<u32>]| sizes.iter().filter(|s| s.is_none()).count() as u32;
+    let section_width =
+        |sizes: &[Option<u32>], flex: u32| -> u32 { sizes.iter().map(|s| s.unwrap_or(flex)).sum() };
+
+    let free =
+        width.saturating_sub(static_width(left) + static_width(center) + static_width(right));
+    let (left_flex, center_flex, right_flex) = if center.is_empty() || flex_count(center) > 0 {
+        let flex = free / (flex_count(left) + flex_count(center) + flex_count(right)).max(1);
+        (flex, flex, flex)
+    } else {
+        // flex widgets on the sides fill the space up to the centered section
+        let center_start = width.saturating_sub(static_width(center)) / 2;
+        let center_end = center_start + static_width(center);
+        (
+            center_start.saturating_sub(static_width(left)) / flex_count(left).max(1),
+            0,
+            width
+                .saturating_sub(center_end)
+                .saturating_sub(static_width(right))
+                / flex_count(right).max(1),
+        )
+    };
+
+    let left_end = section_width(left, left_flex).min(width);
+    let right_start = width
+        .saturating_sub(section_width(right, right_flex))
+        .max(left_end);
+    let center_width = section_width(center, center_flex);
+    let center_start = (width.saturating_sub(center_width) / 2).clamp(
+        left_end,
+        right_start.saturating_sub(center_width).max(left_end),
+    );
+
+    let place = |sizes: &[Option<u32>], flex: u32, start: u32, limit: u32| {
+        let mut x = start;
+        sizes
+            .iter()
+            .map(|size| {
+                let widget_width = size.unwrap_or(flex);
+                let rectangle = Rectangle {
+                    x: x.min(limit),
+                    y: 0,
+                    width: widget_width.min(limit.saturating_sub(x)),
+                    height,
+                };
+                x += widget_width;
+                rectangle
+            })
+            .collect()
+    };
+
+    [
+        place(left, left_flex, 0, width),
+        place(center, center_flex, center_start, right_start),
+        place(right, right_flex, right_start, width),
+    ]
 }
 
-fn bar_event_listener(connection: Arc<Connection>) -> Result<Receiver<()>> {
-    let (tx, rx) = bounded(10);
-    thread::spawn(move || loop {
-        if matches!(connection.wait_for_event(), Ok(Event::X(_))) && tx.send_blocking(()).is_err() {
-            error!("bar_event_listener channel closed");
-            break;
+/// Sends a message when the file at `path` is written or replaced, or when SIGHUP is received
+fn reload_listener(path: &Path) -> Result<Receiver<()>> {
+    let (tx, rx) = bounded(1);
+
+    let mut hangup = Signals::new([SIGHUP])?;
+    let signal_tx = tx.clone();
+    spawn(async move {
+        while hangup.next().await.is_some() {
+            // a reload is already pending otherwise
+            if signal_tx.try_send(()).is_err() && signal_tx.is_closed() {
+                break;
+            }
+        }
+    });
+
+    // editors often replace the file, so the directory is watched instead
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+        _ => PathBuf::from("."),
+    };
+    let file_name = path.file_name().map(ToOwned::to_owned);
+    let mut inotify = Inotify::init()?;
+    inotify
+        .watches()
+        .add(&directory, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)?;
+    thread::spawn(move || {
+        let mut buffer = [0; 4096];
+        loop {
+            let events = match inotify.read_events_blocking(&mut buffer) {
+                Ok(events) => events,
+                Err(e) => {
+                    error!("Stopped watching {:?}: {}", directory, e);
+                    break;
+                }
+            };
+            if events.into_iter().any(|e| e.name == file_name.as_deref())
+                && tx.try_send(()).is_err()
+                && tx.is_closed()
+            {
+                break;
+            }
         }
     });
     Ok(rx)
@@ -465,3 +1241,112 @@ fn notify(signals: &[c_int]) -> std::result::Result<Receiver<c_int>, BarustError
     });
     Ok(r)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::Text;
+    use std::fs::File;
+
+    fn rectangles(spans: &[(u32, u32)]) -> Vec<Rectangle> {
+        spans
+            .iter()
+            .map(|&(x, width)| Rectangle {
+                x,
+                y: 0,
+                width,
+                height: 10,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn layout_centers_the_center_section() {
+        let sizes = [vec![Some(10)], vec![Some(20)], vec![Some(10)]];
+        let [left, center, right] = layout(100, 10, &sizes);
+        assert_eq!(left, rectangles(&[(0, 10)]));
+        assert_eq!(center, rectangles(&[(40, 20)]));
+        assert_eq!(right, rectangles(&[(90, 10)]));
+    }
+
+    #[test]
+    fn layout_flex_widgets_share_the_free_space() {
+        let sizes = [vec![Some(10), None], vec![], vec![None, Some(20)]];
+        let [left, center, right] = layout(100, 10, &sizes);
+        assert_eq!(left, rectangles(&[(0, 10), (10, 35)]));
+        assert!(center.is_empty());
+        assert_eq!(right, rectangles(&[(45, 35), (80, 20)]));
+    }
+
+    #[test]
+    fn layout_flex_widgets_fill_up_to_the_center() {
+        let sizes = [vec![None], vec![Some(20)], vec![Some(10), None]];
+        let [left, center, right] = layout(100, 10, &sizes);
+        assert_eq!(left, rectangles(&[(0, 40)]));
+        assert_eq!(center, rectangles(&[(40, 20)]));
+        assert_eq!(right, rectangles(&[(60, 10), (70, 30)]));
+    }
+
+    #[test]
+    fn layout_flex_center_widget() {
+        let sizes = [vec![Some(20)], vec![None], vec![Some(20)]];
+        let [left, center, right] = layout(100, 10, &sizes);
+        assert_eq!(left, rectangles(&[(0, 20)]));
+        assert_eq!(center, rectangles(&[(20, 60)]));
+        assert_eq!(right, rectangles(&[(80, 20)]));
+    }
+
+    #[test]
+    fn layout_zero_width_widgets() {
+        let sizes = [vec![Some(0), Some(10)], vec![Some(0)], vec![Some(0)]];
+        let [left, center, right] = layout(100, 10, &sizes);
+        assert_eq!(left, rectangles(&[(0, 0), (0, 10)]));
+        assert_eq!(center, rectangles(&[(50, 0)]));
+        assert_eq!(right, rectangles(&[(100, 0)]));
+    }
+
+    #[test]
+    fn layout_center_is_pushed_and_clipped_by_the_sides() {
+        let sizes = [vec![Some(60)], vec![Some(30)], vec![Some(30)]];
+        let [left, center, right] = layout(100, 10, &sizes);
+        assert_eq!(left, rectangles(&[(0, 60)]));
+        // the center section starts after the left one and is cut by the right one
+        assert_eq!(center, rectangles(&[(60, 10)]));
+        assert_eq!(right, rectangles(&[(70, 30)]));
+    }
+
+    #[test]
+    fn layout_clips_widgets_wider_than_the_bar() {
+        let sizes = [vec![Some(80), Some(40)], vec![], vec![Some(10)]];
+        let [left, _, right] = layout(100, 10, &sizes);
+        assert_eq!(left, rectangles(&[(0, 80), (80, 20)]));
+        assert_eq!(right, rectangles(&[(100, 0)]));
+    }
+
+    #[tokio::test]
+    async fn headless_render_writes_png() {
+        let config = WidgetConfig::default();
+        let mut bar = StatusBar::create()
+            .width(200)
+            .height(20)
+            .background(Color::new(1.0, 0.0, 0.0, 1.0))
+            .without_socket()
+            .left_widget(Text::new("hello", &config).await)
+            .build_headless()
+            .await
+            .unwrap();
+        bar.render().await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bar.png");
+        bar.write_png(&path).unwrap();
+
+        let mut image = ImageSurface::create_from_png(&mut File::open(&path).unwrap()).unwrap();
+        assert_eq!((image.width(), image.height()), (200, 20));
+        let stride = image.stride() as usize;
+        let data = image.data().unwrap();
+        // the right end is left to the background, ARGB32 is stored as BGRA
+        let pixel = &data[10 * stride + 199 * 4..][..4];
+        assert_eq!(pixel, [0, 0, 255, 255]);
+    }
+}
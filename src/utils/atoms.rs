@@ -17,9 +17,18 @@ atoms!(
         _NET_SYSTEM_TRAY_ORIENTATION,
         _NET_SYSTEM_TRAY_S0,
         _NET_SYSTEM_TRAY_VISUAL,
+        _NET_WM_DESKTOP,
         _NET_WM_NAME,
+        _NET_WM_STATE,
+        _NET_WM_STATE_ABOVE,
+        _NET_WM_STATE_STICKY,
+        _NET_WM_STRUT,
+        _NET_WM_STRUT_PARTIAL,
         _NET_WM_WINDOW_TYPE,
         _NET_WM_WINDOW_TYPE_DOCK,
+        _NET_WM_WINDOW_TYPE_NOTIFICATION,
+        _NET_WM_WINDOW_TYPE_POPUP_MENU,
+        _NET_WM_WINDOW_TYPE_TOOLTIP,
         _XEMBED,
         _XEMBED_EMBEDDED_NOTIFY,
     }
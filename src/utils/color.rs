@@ -1,6 +1,7 @@
 use cairo::Context;
+use std::str::FromStr;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: f64,
     pub g: f64,
@@ -14,6 +15,29 @@ impl Color {
     }
 }
 
+impl FromStr for Color {
+    type Err = InvalidColor;
+
+    /// Parses colors in the `#rrggbb` or `#rrggbbaa` format
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| matches!(hex.len(), 6 | 8) && hex.is_ascii())
+            .ok_or_else(|| InvalidColor(s.to_string()))?;
+        let channel = |i: usize| -> Result<f64, InvalidColor> {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map(|v| f64::from(v) / 255.0)
+                .map_err(|_| InvalidColor(s.to_string()))
+        };
+        let a = if hex.len() == 8 { channel(6)? } else { 1.0 };
+        Ok(Self::new(channel(0)?, channel(2)?, channel(4)?, a))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid color {0}, expected #rrggbb or #rrggbbaa")]
+pub struct InvalidColor(String);
+
 pub fn set_source_rgba(context: &Context, color: Color) {
     context.set_source_rgba(color.r, color.g, color.b, color.a);
 }
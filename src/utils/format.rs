@@ -0,0 +1,591 @@
+//! Format strings shared by the widgets
+//!
+//! * `{name}` is replaced by the value called `name`
+//! * `{name:spec}` formats it following `[[fill]align][width][.precision][unit]`,
+//!   `align` is one of `<`, `^`, `>`, `unit` is one of `B`, `K`, `M`, `G`, `T` and only applies to sizes
+//! * `{?name:text}` shows `text` only if `name` is true, non zero or not empty, `{!name:text}` only if it is not,
+//!   `text` can contain other placeholders
+//! * `{{` and `}}` are a literal `{` and `}`
+//!
+//! Widgets can also keep the old `%x` tokens as aliases of a placeholder.
+
+use psutil::Bytes;
+
+const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+/// A value substituted in a [Template]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(f64),
+    /// a size, formatted with the closest unit unless the placeholder sets one
+    Bytes(Bytes),
+    Bool(bool),
+}
+
+impl Value {
+    fn is_truthy(&self) -> bool {
+        match self {
+            Self::Text(text) => !text.is_empty(),
+            Self::Number(n) => *n != 0.0,
+            Self::Bytes(b) => *b != 0,
+            Self::Bool(b) => *b,
+        }
+    }
+
+    fn format(&self, spec: &Spec) -> String {
+        match (self, spec.precision) {
+            (Self::Text(text), Some(precision)) => text.chars().take(precision).collect(),
+            (Self::Text(text), None) => text.clone(),
+            (Self::Number(n), Some(precision)) => format!("{:.*}", precision, n),
+            (Self::Number(n), None) => n.to_string(),
+            (Self::Bytes(b), _) => format_bytes(*b, spec.unit, spec.precision),
+            (Self::Bool(b), _) => b.to_string(),
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Self::Number(n)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(n: f32) -> Self {
+        // widening would turn 47.3 into 47.29999923706055, the shortest decimal is kept instead
+        Self::Number(n.to_string().parse().unwrap_or(n.into()))
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+fn format_bytes(value: Bytes, unit: Option<usize>, precision: Option<usize>) -> String {
+    let unit = unit.unwrap_or_else(|| {
+        let mut unit = 0;
+        let mut scaled = value;
+        while scaled > 1024 && unit < UNITS.len() - 1 {
+            scaled /= 1024;
+            unit += 1;
+        }
+        unit
+    });
+    match precision {
+        Some(precision) => {
+            let scaled = value as f64 / 1024_f64.powi(unit as i32);
+            format!("{:.*}{}", precision, scaled, UNITS[unit])
+        }
+        // whole units, truncated
+        None => format!("{}{}", value / 1024_u64.pow(unit as u32), UNITS[unit]),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<Align>,
+    width: usize,
+    precision: Option<usize>,
+    /// index in [UNITS]
+    unit: Option<usize>,
+}
+
+impl Spec {
+    fn parse(s: &str) -> Option<Self> {
+        let align = |c: char| match c {
+            '<' => Some(Align::Left),
+            '^' => Some(Align::Center),
+            '>' => Some(Align::Right),
+            _ => None,
+        };
+        let mut spec = Spec::default();
+        let mut rest = s;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(fill), Some(a)) if align(a).is_some() => {
+                spec.fill = Some(fill);
+                spec.align = align(a);
+                rest = &s[fill.len_utf8() + 1..];
+            }
+            (Some(a), _) if align(a).is_some() => {
+                spec.align = align(a);
+                rest = &s[1..];
+            }
+            _ => {}
+        }
+        let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let end = digits(rest);
+        if end > 0 {
+            spec.width = rest[..end].parse().ok()?;
+        }
+        rest = &rest[end..];
+        if let Some(precision) = rest.strip_prefix('.') {
+            let end = digits(precision);
+            spec.precision = Some(precision[..end].parse().ok()?);
+            rest = &precision[end..];
+        }
+        if !rest.is_empty() {
+            spec.unit = Some(["B", "K", "M", "G", "T"].iter().position(|u| *u == rest)?);
+        }
+        Some(spec)
+    }
+
+    fn pad(&self, text: String, default_align: Align) -> String {
+        let len = text.chars().count();
+        if len >= self.width {
+            return text;
+        }
+        let fill = self.fill.unwrap_or(' ');
+        let missing = self.width - len;
+        let (before, after) = match self.align.unwrap_or(default_align) {
+            Align::Left => (0, missing),
+            Align::Center => (missing / 2, missing - missing / 2),
+            Align::Right => (missing, 0),
+        };
+        let fill = |n| std::iter::repeat_n(fill, n);
+        fill(before)
+            .chain(text.chars())
+            .chain(fill(after))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder {
+        name: String,
+        spec: Spec,
+        /// the text of the placeholder, shown if there is no value called `name`
+        source: String,
+    },
+    Conditional {
+        name: String,
+        negated: bool,
+        body: Template,
+    },
+}
+
+/// A parsed format string, see the [module](self) documentation for the syntax
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(format: &str) -> Self {
+        Self::with_aliases(format, &[])
+    }
+
+    /// Parses `format` replacing the legacy tokens, e.g. `("%p", "{percent:.1}")`,
+    /// longer tokens are matched first so that `%icon` is not read as `%i`
+    pub fn with_aliases(format: &str, aliases: &[(&str, &str)]) -> Self {
+        let mut aliases = aliases.to_vec();
+        aliases.sort_by_key(|(token, _)| std::cmp::Reverse(token.len()));
+        let mut template = Self::default();
+        template.parse_into(format, &aliases);
+        template
+    }
+
+    fn parse_into(&mut self, format: &str, aliases: &[(&str, &str)]) {
+        let mut rest = format;
+        while let Some(c) = rest.chars().next() {
+            if c == '%' {
+                if let Some((token, replacement)) =
+                    aliases.iter().find(|(token, _)| rest.starts_with(token))
+                {
+                    self.parse_into(replacement, &[]);
+                    rest = &rest[token.len()..];
+                    continue;
+                }
+            }
+            if let Some(escaped) = rest.strip_prefix("{{").or_else(|| rest.strip_prefix("}}")) {
+                self.push_literal(&rest[..1]);
+                rest = escaped;
+                continue;
+            }
+            if c == '{' {
+                if let Some((segment, len)) = parse_placeholder(rest, aliases) {
+                    self.segments.push(segment);
+                    rest = &rest[len..];
+                    continue;
+                }
+            }
+            // anything that is not a valid placeholder is kept as it is
+            self.push_literal(&rest[..c.len_utf8()]);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    fn push_literal(&mut self, text: &str) {
+        match self.segments.last_mut() {
+            Some(Segment::Literal(literal)) => literal.push_str(text),
+            _ => self.segments.push(Segment::Literal(text.to_owned())),
+        }
+    }
+
+    /// Replaces the placeholders with `values`
+    pub fn render(&self, values: &[(&str, Value)]) -> String {
+        self.render_escaped(values, str::to_owned)
+    }
+
+    /// Replaces the placeholders with `values`, passing the text values through `escape`
+    pub fn render_escaped(
+        &self,
+        values: &[(&str, Value)],
+        escape: impl Fn(&str) -> String,
+    ) -> String {
+        let mut output = String::new();
+        self.render_into(&mut output, values, &escape);
+        output
+    }
+
+    fn render_into(
+        &self,
+        output: &mut String,
+        values: &[(&str, Value)],
+        escape: &dyn Fn(&str) -> String,
+    ) {
+        let get = |name: &str| values.iter().find(|(n, _)| *n == name).map(|(_, v)| v);
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => output.push_str(literal),
+                Segment::Placeholder { name, spec, source } => match get(name) {
+                    Some(value) => {
+                        let default_align = match value {
+                            Value::Text(_) | Value::Bool(_) => Align::Left,
+                            Value::Number(_) | Value::Bytes(_) => Align::Right,
+                        };
+                        let text = spec.pad(value.format(spec), default_align);
+                        match value {
+                            Value::Text(_) => output.push_str(&escape(&text)),
+                            _ => output.push_str(&text),
+                        }
+                    }
+                    None => output.push_str(source),
+                },
+                Segment::Conditional {
+                    name,
+                    negated,
+                    body,
+                } => {
+                    if get(name).is_some_and(Value::is_truthy) != *negated {
+                        body.render_into(output, values, escape);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Parses the placeholder at the start of `s`, returns it with its length
+fn parse_placeholder(s: &str, aliases: &[(&str, &str)]) -> Option<(Segment, usize)> {
+    let inner = &s[1..];
+    let (negated, conditional) = match inner.chars().next()? {
+        '?' => (false, true),
+        '!' => (true, true),
+        _ => (false, false),
+    };
+    let inner = if conditional { &inner[1..] } else { inner };
+    let name_len = inner
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(inner.len());
+    let name = &inner[..name_len];
+    if name.is_empty() {
+        return None;
+    }
+    let after_name = &inner[name_len..];
+    let offset = s.len() - after_name.len();
+
+    if conditional {
+        let body = after_name.strip_prefix(':')?;
+        let end = matching_brace(body)?;
+        let mut template = Template::default();
+        template.parse_into(&body[..end], aliases);
+        let segment = Segment::Conditional {
+            name: name.to_owned(),
+            negated,
+            body: template,
+        };
+        return Some((segment, offset + 1 + end + 1));
+    }
+
+    let end = after_name.find('}')?;
+    let spec = match &after_name[..end] {
+        "" => Spec::default(),
+        spec => Spec::parse(spec.strip_prefix(':')?)?,
+    };
+    let len = offset + end + 1;
+    let segment = Segment::Placeholder {
+        name: name.to_owned(),
+        spec,
+        source: s[..len].to_owned(),
+    };
+    Some((segment, len))
+}
+
+/// Position of the `}` closing a section, skipping the nested placeholders and the escaped braces
+fn matching_brace(s: &str) -> Option<usize> {
+    let mut depth = 0_usize;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' | '}' if depth == 0 && chars.peek().map(|(_, next)| *next) == Some(c) => {
+                chars.next();
+            }
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::{
+        Battery, Brightness, Cpu, Disk, Memory, Network, Temperatures, Volume, Weather, Wlan,
+    };
+
+    const GB: Bytes = 1024 * 1024 * 1024;
+
+    fn render(format: &str, values: &[(&str, Value)]) -> String {
+        Template::parse(format).render(values)
+    }
+
+    #[test]
+    fn fill_and_alignment() {
+        let values = [("text", "ab".into()), ("number", 3.0.into())];
+        assert_eq!(render("{text:5}|", &values), "ab   |");
+        assert_eq!(render("{number:5}|", &values), "    3|");
+        assert_eq!(render("{text:>5}", &values), "   ab");
+        assert_eq!(render("{number:<5}|", &values), "3    |");
+        assert_eq!(render("{text:*^7}", &values), "**ab***");
+        assert_eq!(render("{number:0>4}", &values), "0003");
+        // too long to be padded
+        assert_eq!(render("{text:1}", &values), "ab");
+    }
+
+    #[test]
+    fn precision() {
+        let values = [
+            ("number", 47.26.into()),
+            ("text", "abcdef".into()),
+            ("single", 47.3_f32.into()),
+        ];
+        assert_eq!(render("{number:.1}", &values), "47.3");
+        assert_eq!(render("{number:.0}", &values), "47");
+        assert_eq!(render("{number: >6.2}", &values), " 47.26");
+        assert_eq!(render("{text:.3}", &values), "abc");
+        // f32 values don't gain digits when widened
+        assert_eq!(render("{single}", &values), "47.3");
+    }
+
+    #[test]
+    fn byte_units() {
+        let values = [
+            ("small", Value::Bytes(512)),
+            ("kilo", Value::Bytes(1536)),
+            ("giga", Value::Bytes(5 * GB)),
+        ];
+        assert_eq!(render("{small}", &values), "512B");
+        assert_eq!(render("{kilo}", &values), "1KB");
+        assert_eq!(render("{kilo:.1}", &values), "1.5KB");
+        assert_eq!(render("{kilo:.3K}", &values), "1.500KB");
+        assert_eq!(render("{giga}", &values), "5GB");
+        assert_eq!(render("{giga:.1M}", &values), "5120.0MB");
+        assert_eq!(render("{giga:>6}", &values), "   5GB");
+    }
+
+    #[test]
+    fn nested_conditionals() {
+        let format = "{?charging:+{?fast: fast}}{!charging:{capacity}%}";
+        let values = |charging: bool, fast: bool| {
+            [
+                ("charging", charging.into()),
+                ("fast", fast.into()),
+                ("capacity", 50.0.into()),
+            ]
+        };
+        assert_eq!(render(format, &values(true, false)), "+");
+        assert_eq!(render(format, &values(true, true)), "+ fast");
+        assert_eq!(render(format, &values(false, true)), "50%");
+        // missing, empty and zero values are false
+        assert_eq!(render("{?missing:x}{!missing:y}", &[]), "y");
+        assert_eq!(render("{?empty:x}", &[("empty", "".into())]), "");
+        assert_eq!(render("{?zero:x}", &[("zero", 0.0.into())]), "");
+    }
+
+    #[test]
+    fn escaped_braces() {
+        let values = [("name", "value".into()), ("set", true.into())];
+        assert_eq!(render("{{name}}", &values), "{name}");
+        assert_eq!(render("{{{name}}}", &values), "{value}");
+        assert_eq!(render("a}}b", &values), "a}b");
+        assert_eq!(render("{?set:{{}}}", &values), "{}");
+    }
+
+    #[test]
+    fn longest_alias_first() {
+        let aliases = [("%i", "{icon}"), ("%icon", "{long}")];
+        let values = [("icon", "I".into()), ("long", "L".into())];
+        let template = Template::with_aliases("%icon %i %x", &aliases);
+        assert_eq!(template.render(&values), "L I %x");
+    }
+
+    #[test]
+    fn invalid_placeholders_are_kept() {
+        let values = [("name", "value".into()), ("set", true.into())];
+        for format in [
+            "{unknown}",
+            "{name:zz}",
+            "{",
+            "{ }",
+            "{name",
+            "{?set}",
+            "{?set:x",
+            "50%",
+        ] {
+            assert_eq!(render(format, &values), format);
+        }
+        assert_eq!(render("{name} {", &values), "value {");
+    }
+
+    #[test]
+    fn text_values_are_escaped() {
+        let values = [("text", "<b>".into()), ("number", 1.0.into())];
+        let escape = |s: &str| s.replace('<', "&lt;").replace('>', "&gt;");
+        let template = Template::parse("<i>{text}</i> {number}");
+        assert_eq!(
+            template.render_escaped(&values, escape),
+            "<i>&lt;b&gt;</i> 1"
+        );
+    }
+
+    /// Renders the default format and the legacy one it replaced, they must match `expected`
+    fn check_default(
+        default: &str,
+        legacy: &str,
+        aliases: &[(&str, &str)],
+        values: &[(&str, Value)],
+        expected: &str,
+    ) {
+        assert_eq!(
+            Template::with_aliases(default, aliases).render(values),
+            expected
+        );
+        assert_eq!(
+            Template::with_aliases(legacy, aliases).render(values),
+            expected
+        );
+    }
+
+    #[test]
+    fn widget_default_formats() {
+        check_default(
+            Battery::DEFAULT_FORMAT,
+            "%i %c%",
+            Battery::ALIASES,
+            &[("capacity", 42.0.into()), ("icon", "B".into())],
+            "B 42%",
+        );
+        check_default(
+            Brightness::DEFAULT_FORMAT,
+            "%i %p%",
+            Brightness::ALIASES,
+            &[("percent", 55.4.into()), ("icon", "S".into())],
+            "S 55%",
+        );
+        check_default(
+            Cpu::DEFAULT_FORMAT,
+            "%p%",
+            Cpu::ALIASES,
+            &[("percent", 7.3_f32.into())],
+            " 7.3%",
+        );
+        check_default(
+            Disk::DEFAULT_FORMAT,
+            "%f",
+            Disk::ALIASES,
+            &[("free", Value::Bytes(20 * GB))],
+            "20GB",
+        );
+        check_default(
+            Memory::DEFAULT_FORMAT,
+            "%p%",
+            Memory::ALIASES,
+            &[("percent", 43.219_f32.into())],
+            "43.22%",
+        );
+        check_default(
+            Network::DEFAULT_FORMAT,
+            "%n %s",
+            Network::ALIASES,
+            &[("interface", "eth0".into()), ("status", "up".into())],
+            "eth0 up",
+        );
+        check_default(
+            Temperatures::DEFAULT_FORMAT,
+            "%t°C",
+            Temperatures::ALIASES,
+            &[("temperature", 45.67.into())],
+            "45.7°C",
+        );
+        check_default(
+            Volume::DEFAULT_FORMAT,
+            "%i %p",
+            Volume::ALIASES,
+            &[("percent", 30.0.into()), ("icon", "V".into())],
+            "V 30.0",
+        );
+        check_default(
+            Weather::DEFAULT_FORMAT,
+            "%city %icon %cur",
+            Weather::ALIASES,
+            &[
+                ("city", "Rome".into()),
+                ("icon", "*".into()),
+                ("current", 21.5.into()),
+            ],
+            "Rome * 21.5",
+        );
+        check_default(
+            Wlan::DEFAULT_FORMAT,
+            "%e",
+            Wlan::ALIASES,
+            &[("essid", "home".into())],
+            "home",
+        );
+    }
+
+    #[test]
+    fn disk_percent_alias() {
+        let template = Template::with_aliases("%p%", Disk::ALIASES);
+        assert_eq!(template.render(&[("percent", 47.3_f32.into())]), "47.3%");
+    }
+}
@@ -1,12 +1,14 @@
 use async_channel::{SendError, Sender};
 
+/// Section of the bar a widget belongs to
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum RightLeft {
-    Right,
+pub enum Side {
     Left,
+    Center,
+    Right,
 }
 
-pub type WidgetID = (RightLeft, usize);
+pub type WidgetID = (Side, usize);
 
 #[derive(Debug, Clone)]
 pub struct HookSender {
@@ -26,4 +28,9 @@ impl HookSender {
     pub fn send_blocking(&self) -> Result<(), SendError<WidgetID>> {
         self.sender.send_blocking(self.id)
     }
+
+    /// Returns true once the bar stopped listening, e.g. after a reload
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
 }
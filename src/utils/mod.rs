@@ -1,28 +1,57 @@
 use psutil::Bytes;
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 use xcb::Connection;
 
 pub mod atoms;
 pub mod color;
+pub mod format;
 pub mod hook_sender;
+pub mod monitor;
+pub mod notifications;
 pub mod resettable_timer;
+pub mod style;
+pub mod thresholds;
 pub mod timed_hooks;
 
 pub use atoms::Atoms;
 pub use color::{set_source_rgba, Color};
-pub use hook_sender::{HookSender, WidgetID};
+pub use format::Template;
+pub use hook_sender::{HookSender, Side, WidgetID};
+pub use monitor::{find_monitor, monitors, screen_size, Monitor};
 pub use resettable_timer::ResettableTimer;
+pub use style::{Separator, Style};
+pub use thresholds::Thresholds;
 pub use timed_hooks::TimedHooks;
 
-#[derive(Debug)]
 pub struct StatusBarInfo {
     pub background: Color,
     pub left_regions: Vec<Rectangle>,
+    pub center_regions: Vec<Rectangle>,
     pub right_regions: Vec<Rectangle>,
     pub height: u32,
     pub width: u32,
     pub position: Position,
     pub window: xcb::x::Window,
+    /// X connection of the bar, `None` if it is not displayed by the X server
+    pub connection: Option<Arc<Connection>>,
+    /// monitor of the bar, `None` if it is not displayed by the X server
+    pub monitor: Option<Monitor>,
+}
+
+impl Debug for StatusBarInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StatusBarInfo")
+            .field("background", &self.background)
+            .field("left_regions", &self.left_regions)
+            .field("center_regions", &self.center_regions)
+            .field("right_regions", &self.right_regions)
+            .field("height", &self.height)
+            .field("width", &self.width)
+            .field("position", &self.position)
+            .field("window", &self.window)
+            .field("monitor", &self.monitor)
+            .finish_non_exhaustive()
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -31,6 +60,92 @@ pub enum Position {
     Bottom,
 }
 
+impl std::str::FromStr for Position {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top" => Ok(Self::Top),
+            "bottom" => Ok(Self::Bottom),
+            _ => Err(format!("Invalid position {}, expected top or bottom", s)),
+        }
+    }
+}
+
+/// A pointer button as reported by X11, buttons 4 to 7 are used for scrolling
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+    Other(u8),
+}
+
+impl From<u8> for MouseButton {
+    fn from(button: u8) -> Self {
+        match button {
+            1 => Self::Left,
+            2 => Self::Middle,
+            3 => Self::Right,
+            4 => Self::ScrollUp,
+            5 => Self::ScrollDown,
+            6 => Self::ScrollLeft,
+            7 => Self::ScrollRight,
+            b => Self::Other(b),
+        }
+    }
+}
+
+/// A key pressed while a [Popup](crate::statusbar::Popup) is open
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Escape,
+    Enter,
+    Tab,
+    Backspace,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Char(char),
+    /// any other keysym
+    Other(u32),
+}
+
+impl From<u32> for Key {
+    /// Translates an X keysym
+    fn from(keysym: u32) -> Self {
+        let char = match keysym {
+            0xff1b => return Self::Escape,
+            0xff0d | 0xff8d => return Self::Enter,
+            0xff09 => return Self::Tab,
+            0xff08 => return Self::Backspace,
+            0xff52 => return Self::Up,
+            0xff54 => return Self::Down,
+            0xff51 => return Self::Left,
+            0xff53 => return Self::Right,
+            0xff55 => return Self::PageUp,
+            0xff56 => return Self::PageDown,
+            0xff50 => return Self::Home,
+            0xff57 => return Self::End,
+            // Latin-1 keysyms are the same as the code points
+            0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+            // the other code points are offset by 0x01000000
+            0x0100_0100..=0x0110_ffff => char::from_u32(keysym - 0x0100_0000),
+            _ => None,
+        };
+        char.map_or(Self::Other(keysym), Self::Char)
+    }
+}
+
 pub fn screen_true_width(connection: &Connection, screen_id: i32) -> u16 {
     connection
         .get_setup()
@@ -71,7 +186,7 @@ pub fn bytes_to_closest(value: Bytes) -> String {
     format!("{}{}", value, units[selected_unit])
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Rectangle {
     pub x: u32,
     pub y: u32,
@@ -79,6 +194,23 @@ pub struct Rectangle {
     pub height: u32,
 }
 
+impl Rectangle {
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    pub fn intersects(&self, other: &Rectangle) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
 impl From<Rectangle> for cairo::Rectangle {
     fn from(r: Rectangle) -> Self {
         cairo::Rectangle {
@@ -0,0 +1,112 @@
+use xcb::{
+    randr::{self, Connection as OutputConnection},
+    x::{Drawable, GetGeometry, Window},
+    Connection, Extension, Xid,
+};
+
+/// A physical output as reported by RandR
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monitor {
+    pub name: String,
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+    pub primary: bool,
+}
+
+impl Monitor {
+    /// Connects to the X server and lists all the active monitors
+    pub fn all() -> xcb::Result<Vec<Monitor>> {
+        let (connection, screen_id) =
+            Connection::connect_with_extensions(None, &[], &[Extension::RandR])?;
+        monitors(&connection, screen_id)
+    }
+}
+
+fn root_window(connection: &Connection, screen_id: i32) -> Window {
+    connection
+        .get_setup()
+        .roots()
+        .nth(screen_id as _)
+        .unwrap_or_else(|| panic!("cannot find screen:{}", screen_id))
+        .root()
+}
+
+/// Current size of the whole X screen, unlike the connection setup it follows RandR changes
+pub fn screen_size(connection: &Connection, screen_id: i32) -> xcb::Result<(u16, u16)> {
+    let geometry = connection.wait_for_reply(connection.send_request(&GetGeometry {
+        drawable: Drawable::Window(root_window(connection, screen_id)),
+    }))?;
+    Ok((geometry.width(), geometry.height()))
+}
+
+/// Lists the connected outputs that are driven by a crtc,
+/// falls back to a single monitor covering the screen if RandR is not available
+pub fn monitors(connection: &Connection, screen_id: i32) -> xcb::Result<Vec<Monitor>> {
+    if !connection
+        .active_extensions()
+        .any(|ext| ext == Extension::RandR)
+    {
+        return fallback_monitor(connection, screen_id);
+    }
+
+    let root = root_window(connection, screen_id);
+    let resources = connection.wait_for_reply(
+        connection.send_request(&randr::GetScreenResourcesCurrent { window: root }),
+    )?;
+    let primary = connection
+        .wait_for_reply(connection.send_request(&randr::GetOutputPrimary { window: root }))?
+        .output();
+
+    let mut monitors = Vec::new();
+    for output in resources.outputs() {
+        let info = connection.wait_for_reply(connection.send_request(&randr::GetOutputInfo {
+            output: *output,
+            config_timestamp: resources.config_timestamp(),
+        }))?;
+        if info.connection() != OutputConnection::Connected || info.crtc().is_none() {
+            continue;
+        }
+        let crtc = connection.wait_for_reply(connection.send_request(&randr::GetCrtcInfo {
+            crtc: info.crtc(),
+            config_timestamp: resources.config_timestamp(),
+        }))?;
+        monitors.push(Monitor {
+            name: String::from_utf8_lossy(info.name()).to_string(),
+            x: crtc.x(),
+            y: crtc.y(),
+            width: crtc.width(),
+            height: crtc.height(),
+            primary: *output == primary,
+        });
+    }
+
+    if monitors.is_empty() {
+        return fallback_monitor(connection, screen_id);
+    }
+    Ok(monitors)
+}
+
+fn fallback_monitor(connection: &Connection, screen_id: i32) -> xcb::Result<Vec<Monitor>> {
+    let (width, height) = screen_size(connection, screen_id)?;
+    Ok(vec![Monitor {
+        name: String::from("default"),
+        x: 0,
+        y: 0,
+        width,
+        height,
+        primary: true,
+    }])
+}
+
+/// Selects the monitor named `output`, or the primary one if `output` is `None`
+pub fn find_monitor(monitors: Vec<Monitor>, output: Option<&str>) -> Option<Monitor> {
+    match output {
+        Some(name) => monitors.into_iter().find(|m| m.name == name),
+        None => {
+            let primary = monitors.iter().position(|m| m.primary).unwrap_or(0);
+            monitors.into_iter().nth(primary)
+        }
+    }
+}
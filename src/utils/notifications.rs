@@ -0,0 +1,349 @@
+//! Client of the desktop notification server, `org.freedesktop.Notifications`
+//!
+//! [Notifier::with_connection] sends the notifications on another bus than the session bus,
+//! e.g. a private bus with a stub server.
+
+use futures_util::{Stream, StreamExt};
+use std::{collections::HashMap, str::FromStr, time::Duration};
+use zbus::{zvariant::Value, Connection, Proxy};
+
+/// Urgency of a notification, the values are the ones of the specification
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Urgency {
+    Low = 0,
+    #[default]
+    Normal = 1,
+    Critical = 2,
+}
+
+impl FromStr for Urgency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Self::Low),
+            "normal" => Ok(Self::Normal),
+            "critical" => Ok(Self::Critical),
+            other => Err(format!(
+                "unknown urgency {}, expected low, normal or critical",
+                other
+            )),
+        }
+    }
+}
+
+/// How long a notification stays on screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Timeout {
+    /// chosen by the server
+    #[default]
+    Default,
+    /// until it is closed
+    Never,
+    After(Duration),
+}
+
+impl Timeout {
+    fn millis(self) -> i32 {
+        match self {
+            Self::Default => -1,
+            Self::Never => 0,
+            Self::After(duration) => duration.as_millis().clamp(1, i32::MAX as u128) as i32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    summary: String,
+    body: String,
+    icon: String,
+    urgency: Urgency,
+    timeout: Timeout,
+    /// pairs of key and label
+    actions: Vec<(String, String)>,
+    hints: HashMap<String, Value<'static>>,
+    replaces: u32,
+}
+
+impl Notification {
+    pub fn new(summary: impl ToString) -> Self {
+        Self {
+            summary: summary.to_string(),
+            body: String::new(),
+            icon: String::new(),
+            urgency: Urgency::default(),
+            timeout: Timeout::default(),
+            actions: Vec::new(),
+            hints: HashMap::new(),
+            replaces: 0,
+        }
+    }
+
+    pub fn body(mut self, body: impl ToString) -> Self {
+        self.body = body.to_string();
+        self
+    }
+
+    /// An icon name from the theme or a `file://` URI
+    pub fn icon(mut self, icon: impl ToString) -> Self {
+        self.icon = icon.to_string();
+        self
+    }
+
+    pub fn urgency(mut self, urgency: Urgency) -> Self {
+        self.urgency = urgency;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Timeout) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Adds a button, its `key` is reported by [Notifier::action_invoked] when clicked,
+    /// `default` is the key of a click on the notification itself
+    pub fn action(mut self, key: impl ToString, label: impl ToString) -> Self {
+        self.actions.push((key.to_string(), label.to_string()));
+        self
+    }
+
+    /// Sets a hint for the server, e.g. `("value", 50_i32)` for a progress bar
+    pub fn hint(mut self, name: impl ToString, value: impl Into<Value<'static>>) -> Self {
+        self.hints.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Replaces the notification with the id returned by [Notifier::send] instead of stacking
+    pub fn replaces(mut self, id: u32) -> Self {
+        self.replaces = id;
+        self
+    }
+}
+
+/// Sends [Notification]s to the notification server
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    proxy: Proxy<'static>,
+    app_name: String,
+}
+
+impl Notifier {
+    /// Connects to the session bus
+    pub async fn new() -> zbus::Result<Self> {
+        Self::with_connection(Connection::session().await?).await
+    }
+
+    pub async fn with_connection(connection: Connection) -> zbus::Result<Self> {
+        let proxy = Proxy::new(
+            &connection,
+            "org.freedesktop.Notifications",
+            "/org/freedesktop/Notifications",
+            "org.freedesktop.Notifications",
+        )
+        .await?;
+        Ok(Self {
+            proxy,
+            app_name: String::from("barust"),
+        })
+    }
+
+    /// Shows `notification`, returns its id
+    pub async fn send(&self, notification: &Notification) -> zbus::Result<u32> {
+        let urgency = Value::U8(notification.urgency as u8);
+        let mut hints: HashMap<&str, &Value> = notification
+            .hints
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .collect();
+        hints.entry("urgency").or_insert(&urgency);
+        let actions: Vec<&str> = notification
+            .actions
+            .iter()
+            .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+            .collect();
+        self.proxy
+            .call(
+                "Notify",
+                &(
+                    self.app_name.as_str(),
+                    notification.replaces,
+                    notification.icon.as_str(),
+                    notification.summary.as_str(),
+                    notification.body.as_str(),
+                    actions,
+                    hints,
+                    notification.timeout.millis(),
+                ),
+            )
+            .await
+    }
+
+    pub async fn close(&self, id: u32) -> zbus::Result<()> {
+        self.proxy.call("CloseNotification", &(id,)).await
+    }
+
+    /// The ids of the notifications and the keys of the actions clicked by the user
+    pub async fn action_invoked(&self) -> zbus::Result<impl Stream<Item = (u32, String)>> {
+        let signals = self.proxy.receive_signal("ActionInvoked").await?;
+        Ok(signals.filter_map(|message| async move { message.body::<(u32, String)>().ok() }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::UnixStream;
+    use zbus::{dbus_interface, zvariant::OwnedValue, ConnectionBuilder, Guid, SignalContext};
+
+    /// The arguments of a Notify call
+    #[derive(Debug)]
+    struct Call {
+        app_name: String,
+        replaces_id: u32,
+        icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        timeout: i32,
+    }
+
+    /// Stub notification server recording the calls
+    #[derive(Default)]
+    struct Server {
+        calls: Arc<Mutex<Vec<Call>>>,
+    }
+
+    #[dbus_interface(name = "org.freedesktop.Notifications")]
+    impl Server {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            app_name: String,
+            replaces_id: u32,
+            icon: String,
+            summary: String,
+            body: String,
+            actions: Vec<String>,
+            hints: HashMap<String, OwnedValue>,
+            timeout: i32,
+        ) -> u32 {
+            let mut calls = self.calls.lock().unwrap();
+            calls.push(Call {
+                app_name,
+                replaces_id,
+                icon,
+                summary,
+                body,
+                actions,
+                hints,
+                timeout,
+            });
+            calls.len() as u32
+        }
+
+        #[dbus_interface(signal)]
+        async fn action_invoked(
+            context: &SignalContext<'_>,
+            id: u32,
+            action_key: &str,
+        ) -> zbus::Result<()>;
+    }
+
+    /// A [Notifier] connected to a [Server] through a socket pair
+    async fn connect() -> (Notifier, Connection, Arc<Mutex<Vec<Call>>>) {
+        let (server_stream, client_stream) = UnixStream::pair().unwrap();
+        let server = Server::default();
+        let calls = Arc::clone(&server.calls);
+        let guid = Guid::generate();
+        let server = ConnectionBuilder::unix_stream(server_stream)
+            .server(&guid)
+            .p2p()
+            .serve_at("/org/freedesktop/Notifications", server)
+            .unwrap()
+            .build();
+        let client = ConnectionBuilder::unix_stream(client_stream).p2p().build();
+        let (server, client) = futures_util::try_join!(server, client).unwrap();
+        let notifier = Notifier::with_connection(client).await.unwrap();
+        (notifier, server, calls)
+    }
+
+    #[test]
+    fn timeout_millis() {
+        assert_eq!(Timeout::Default.millis(), -1);
+        assert_eq!(Timeout::Never.millis(), 0);
+        assert_eq!(Timeout::After(Duration::from_millis(1500)).millis(), 1500);
+        // 0 would mean never
+        assert_eq!(Timeout::After(Duration::ZERO).millis(), 1);
+        assert_eq!(Timeout::After(Duration::MAX).millis(), i32::MAX);
+    }
+
+    #[tokio::test]
+    async fn notify_arguments() {
+        let (notifier, _server, calls) = connect().await;
+
+        let notification = Notification::new("Low battery")
+            .body("10% left")
+            .icon("battery-low")
+            .urgency(Urgency::Critical)
+            .timeout(Timeout::After(Duration::from_millis(1500)))
+            .action("default", "Open")
+            .action("suspend", "Suspend")
+            .hint("value", 10_i32)
+            .replaces(7);
+        assert_eq!(notifier.send(&notification).await.unwrap(), 1);
+        assert_eq!(notifier.send(&Notification::new("Hello")).await.unwrap(), 2);
+        let explicit = Notification::new("Hint")
+            .urgency(Urgency::Low)
+            .hint("urgency", 2_u8);
+        assert_eq!(notifier.send(&explicit).await.unwrap(), 3);
+
+        let calls = calls.lock().unwrap();
+        let call = &calls[0];
+        assert_eq!(call.app_name, "barust");
+        assert_eq!(call.replaces_id, 7);
+        assert_eq!(call.icon, "battery-low");
+        assert_eq!(call.summary, "Low battery");
+        assert_eq!(call.body, "10% left");
+        assert_eq!(call.actions, ["default", "Open", "suspend", "Suspend"]);
+        assert_eq!(*call.hints["urgency"], Value::U8(2));
+        assert_eq!(*call.hints["value"], Value::I32(10));
+        assert_eq!(call.timeout, 1500);
+
+        let call = &calls[1];
+        assert_eq!(call.replaces_id, 0);
+        assert!(call.actions.is_empty());
+        assert_eq!(call.hints.len(), 1);
+        assert_eq!(*call.hints["urgency"], Value::U8(1));
+        assert_eq!(call.timeout, -1);
+
+        // an explicit hint wins over the urgency
+        assert_eq!(*calls[2].hints["urgency"], Value::U8(2));
+    }
+
+    #[tokio::test]
+    async fn action_invoked_decoding() {
+        let (notifier, server, _calls) = connect().await;
+        let mut actions = Box::pin(notifier.action_invoked().await.unwrap());
+
+        // a signal with unexpected arguments is skipped
+        server
+            .emit_signal(
+                None::<&str>,
+                "/org/freedesktop/Notifications",
+                "org.freedesktop.Notifications",
+                "ActionInvoked",
+                &("suspend",),
+            )
+            .await
+            .unwrap();
+        let context = SignalContext::new(&server, "/org/freedesktop/Notifications").unwrap();
+        Server::action_invoked(&context, 3, "suspend")
+            .await
+            .unwrap();
+
+        assert_eq!(actions.next().await, Some((3, String::from("suspend"))));
+    }
+}
@@ -1,4 +1,6 @@
+use crate::utils::HookSender;
 use std::time::{Duration, Instant};
+use tokio::time::sleep;
 
 #[derive(Debug, Clone, Copy)]
 pub struct ResettableTimer {
@@ -21,4 +23,19 @@ impl ResettableTimer {
     pub fn is_done(&self) -> bool {
         self.timer.elapsed() > self.duration
     }
+
+    /// Restarts the timer and asks `sender` for an update once it is done,
+    /// for the widgets that change when the timer is done but are not polled
+    pub fn reset_and_notify(&mut self, sender: Option<HookSender>) {
+        self.reset();
+        let Some(sender) = sender else {
+            return;
+        };
+        // a bit later, so that the timer is done when the widget is updated
+        let delay = self.duration + Duration::from_millis(10);
+        tokio::spawn(async move {
+            sleep(delay).await;
+            sender.send().await.ok();
+        });
+    }
 }
@@ -0,0 +1,180 @@
+use crate::utils::{set_source_rgba, Color, Rectangle, Side};
+use cairo::Context;
+use std::{f64::consts::PI, str::FromStr};
+
+/// Shape of the powerline-style separator drawn next to a widget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    Arrow,
+    Slant,
+    Round,
+}
+
+impl FromStr for Separator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "arrow" => Ok(Self::Arrow),
+            "slant" => Ok(Self::Slant),
+            "round" => Ok(Self::Round),
+            other => Err(format!(
+                "unknown separator {}, expected arrow, slant or round",
+                other
+            )),
+        }
+    }
+}
+
+/// Decorations painted by the bar in the region of a widget before the widget draws itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    pub background: Option<Color>,
+    pub underline: Option<Color>,
+    pub overline: Option<Color>,
+    /// thickness of the underline and the overline
+    pub accent_width: u32,
+    pub border: Option<Color>,
+    pub border_width: u32,
+    pub corner_radius: f64,
+    /// empty space around the widget
+    pub margin: u32,
+    /// filled with the background, on the right of the widget,
+    /// on its left for the widgets on the right side of the bar
+    pub separator: Option<Separator>,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            background: None,
+            underline: None,
+            overline: None,
+            accent_width: 2,
+            border: None,
+            border_width: 1,
+            corner_radius: 0.0,
+            margin: 0,
+            separator: None,
+        }
+    }
+}
+
+impl Style {
+    fn separator_width(&self, height: u32) -> u32 {
+        match self.separator {
+            Some(_) => height.saturating_sub(2 * self.margin) / 2,
+            None => 0,
+        }
+    }
+
+    /// Width added to the size of the widget by the margin and the separator
+    pub fn extra_width(&self, height: u32) -> u32 {
+        2 * self.margin + self.separator_width(height)
+    }
+
+    /// The part of `region` left to the widget
+    pub fn block(&self, region: &Rectangle, side: Side) -> Rectangle {
+        let separator = self.separator_width(region.height);
+        let x = match side {
+            Side::Right => region.x + self.margin + separator,
+            Side::Left | Side::Center => region.x + self.margin,
+        };
+        Rectangle {
+            x: x.min(region.x + region.width),
+            y: region.y + self.margin,
+            width: region.width.saturating_sub(self.extra_width(region.height)),
+            height: region.height.saturating_sub(2 * self.margin),
+        }
+    }
+
+    /// Paints the decorations of the widget in `region`, `context` covers the whole bar
+    pub fn draw(&self, context: &Context, region: &Rectangle, side: Side) -> cairo::Result<()> {
+        let block = self.block(region, side);
+        if block.is_empty() {
+            return Ok(());
+        }
+        let (x, y, width, height) = (
+            f64::from(block.x),
+            f64::from(block.y),
+            f64::from(block.width),
+            f64::from(block.height),
+        );
+
+        if let Some(background) = self.background {
+            set_source_rgba(context, background);
+            rounded_rectangle(context, x, y, width, height, self.corner_radius);
+            context.fill()?;
+            if let Some(separator) = self.separator {
+                let (base, tip) = match side {
+                    Side::Right => (x, x - height / 2.0),
+                    Side::Left | Side::Center => (x + width, x + width + height / 2.0),
+                };
+                context.move_to(base, y);
+                match separator {
+                    Separator::Arrow => context.line_to(tip, y + height / 2.0),
+                    Separator::Slant => context.line_to(tip, y),
+                    Separator::Round => context.curve_to(tip, y, tip, y + height, base, y + height),
+                }
+                context.line_to(base, y + height);
+                context.close_path();
+                context.fill()?;
+            }
+        }
+
+        if let Some(border) = self.border {
+            let line_width = f64::from(self.border_width);
+            set_source_rgba(context, border);
+            context.set_line_width(line_width);
+            rounded_rectangle(
+                context,
+                x + line_width / 2.0,
+                y + line_width / 2.0,
+                width - line_width,
+                height - line_width,
+                self.corner_radius,
+            );
+            context.stroke()?;
+        }
+
+        let accent_width = f64::from(self.accent_width);
+        if let Some(overline) = self.overline {
+            set_source_rgba(context, overline);
+            context.rectangle(x, y, width, accent_width);
+            context.fill()?;
+        }
+        if let Some(underline) = self.underline {
+            set_source_rgba(context, underline);
+            context.rectangle(x, y + height - accent_width, width, accent_width);
+            context.fill()?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn rounded_rectangle(
+    context: &Context,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    radius: f64,
+) {
+    let radius = radius.min(width / 2.0).min(height / 2.0);
+    if radius <= 0.0 {
+        context.rectangle(x, y, width, height);
+        return;
+    }
+    context.new_sub_path();
+    context.arc(x + width - radius, y + radius, radius, -PI / 2.0, 0.0);
+    context.arc(
+        x + width - radius,
+        y + height - radius,
+        radius,
+        0.0,
+        PI / 2.0,
+    );
+    context.arc(x + radius, y + height - radius, radius, PI / 2.0, PI);
+    context.arc(x + radius, y + radius, radius, PI, 3.0 * PI / 2.0);
+    context.close_path();
+}
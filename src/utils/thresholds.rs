@@ -0,0 +1,59 @@
+use crate::utils::Color;
+
+/// Maps a value to a color, e.g. green from 0, yellow from 50 and red from 80
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Thresholds {
+    /// sorted by value
+    stops: Vec<(f64, Color)>,
+    gradient: bool,
+}
+
+impl Thresholds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `color` for the values greater than or equal to `value`, up to the next stop
+    pub fn at(mut self, value: f64, color: Color) -> Self {
+        let index = self.stops.partition_point(|(v, _)| *v <= value);
+        self.stops.insert(index, (value, color));
+        self
+    }
+
+    /// Blends the colors of the stops around the value instead of switching at each stop
+    pub fn gradient(mut self, gradient: bool) -> Self {
+        self.gradient = gradient;
+        self
+    }
+
+    pub fn is_gradient(&self) -> bool {
+        self.gradient
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stops.is_empty()
+    }
+
+    /// The color of `value`, values below the first stop use its color,
+    /// `None` if there are no stops
+    pub fn color(&self, value: f64) -> Option<Color> {
+        let next = self.stops.partition_point(|(v, _)| *v <= value);
+        let (from, to) = match next {
+            0 => return self.stops.first().map(|(_, c)| *c),
+            n if n == self.stops.len() || !self.gradient => return Some(self.stops[n - 1].1),
+            n => (self.stops[n - 1], self.stops[n]),
+        };
+        let t = (value - from.0) / (to.0 - from.0);
+        Some(mix(from.1, to.1, t))
+    }
+}
+
+fn mix(from: Color, to: Color, t: f64) -> Color {
+    let channel = |a: f64, b: f64| a + (b - a) * t;
+    Color::new(
+        channel(from.r, to.r),
+        channel(from.g, to.g),
+        channel(from.b, to.b),
+        channel(from.a, to.a),
+    )
+}
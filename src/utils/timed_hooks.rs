@@ -1,29 +1,42 @@
 use super::hook_sender::HookSender;
 use log::{debug, error};
-use std::time::Duration;
-use tokio::{task::spawn, time::sleep};
+use std::{collections::BTreeMap, time::Duration};
+use tokio::{
+    task::{spawn, JoinHandle},
+    time::{interval, MissedTickBehavior},
+};
 
+/// Periodically asks the subscribed widgets to update, grouping them by interval
 #[derive(Debug, Default)]
 pub struct TimedHooks {
-    senders: Vec<HookSender>,
+    senders: BTreeMap<Duration, Vec<HookSender>>,
 }
 
 impl TimedHooks {
-    pub fn subscribe(&mut self, sender: HookSender) {
-        self.senders.push(sender);
+    /// Sends an update request through `sender` every `interval`
+    pub fn subscribe(&mut self, interval: Duration, sender: HookSender) {
+        self.senders.entry(interval).or_default().push(sender);
     }
 
-    pub async fn start(self) {
-        let duration = Duration::from_secs(1) / self.senders.len() as u32;
-        spawn(async move {
-            for s in self.senders.into_iter().cycle() {
-                if s.send().await.is_err() {
-                    error!("breaking thread loop")
+    /// Spawns one task per interval, the returned handles can be used to stop them
+    pub async fn start(self) -> Vec<JoinHandle<()>> {
+        let mut tasks = Vec::new();
+        for (period, senders) in self.senders {
+            tasks.push(spawn(async move {
+                let mut timer = interval(period.max(Duration::from_millis(1)));
+                timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                loop {
+                    timer.tick().await;
+                    debug!("waking from {:?} sleep", period);
+                    for s in &senders {
+                        if s.send().await.is_err() {
+                            error!("breaking thread loop");
+                            return;
+                        }
+                    }
                 }
-
-                sleep(duration).await;
-                debug!("waking from sleep");
-            }
-        });
+            }));
+        }
+        tasks
     }
 }
@@ -6,7 +6,7 @@ use crate::{
 use async_trait::async_trait;
 use cairo::Context;
 use log::{debug, error};
-use std::{fmt::Display, sync::Arc, thread};
+use std::{fmt::Display, sync::Arc, thread, time::Duration};
 use xcb::{
     x::{ChangeWindowAttributes, Cw, Event, EventMask, Window},
     Connection, XidNew,
@@ -43,6 +43,7 @@ pub fn get_active_window_name(connection: &Connection) -> Result<String> {
 
 pub struct ActiveWindow {
     inner: Text,
+    update_interval: Duration,
     connection: Connection,
 }
 
@@ -57,6 +58,7 @@ impl ActiveWindow {
         let (connection, _) = Connection::connect(None).map_err(Error::from)?;
         Ok(Box::new(Self {
             inner: *Text::new("", config).await,
+            update_interval: config.update_interval,
             connection,
         }))
     }
@@ -64,12 +66,15 @@ impl ActiveWindow {
 
 #[async_trait]
 impl Widget for ActiveWindow {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating active_window");
-        if let Ok(window_name) = get_active_window_name(&self.connection) {
-            self.inner.set_text(window_name);
+        match get_active_window_name(&self.connection) {
+            Ok(window_name) => {
+                let text = self.inner.escape(&window_name);
+                Ok(self.inner.set_text(text))
+            }
+            Err(_) => Ok(false),
         }
-        Ok(())
     }
 
     async fn hook(&mut self, sender: HookSender, timed_hooks: &mut TimedHooks) -> Result<()> {
@@ -105,11 +110,11 @@ impl Widget for ActiveWindow {
             };
         });
 
-        timed_hooks.subscribe(sender);
+        timed_hooks.subscribe(self.update_interval, sender);
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for ActiveWindow {
@@ -1,16 +1,22 @@
 use crate::{
-    utils::{percentage_to_index, HookSender, TimedHooks},
+    utils::{
+        format::Value,
+        notifications::{Notification, Notifier, Urgency},
+        percentage_to_index, HookSender, Template, TimedHooks,
+    },
     widget_default,
     widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
 };
 use async_trait::async_trait;
 use cairo::Context;
-use log::debug;
+use log::{debug, error, warn};
 use std::{
     fmt::Display,
-    fs::read_dir,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
     time::{Duration, Instant},
 };
+use zbus::Connection;
 
 /// Icons used by [Battery]
 #[derive(Debug)]
@@ -37,87 +43,68 @@ impl Default for BatteryIcons {
 /// Displays status and charge of the battery
 #[derive(Debug)]
 pub struct Battery {
-    format: String,
+    format: Template,
     inner: Text,
-    root_path: String,
+    update_interval: Duration,
+    power_supply: PowerSupply,
     icons: BatteryIcons,
     low_battery_warning: Box<dyn LowBatteryWarner>,
+    status: Option<BatteryStatus>,
 }
 
 impl Battery {
-    ///* `format`
-    ///  * `%c` will be replaced with the charge percentage
-    ///  * `%i` will be replaced with the correct icon from `icons`
+    /// Format used when the config file doesn't set one
+    pub const DEFAULT_FORMAT: &'static str = "{icon} {capacity:.0}%";
+    /// Legacy `%x` tokens of the format
+    pub(crate) const ALIASES: &'static [(&'static str, &'static str)] =
+        &[("%c", "{capacity:.0}"), ("%i", "{icon}")];
+
+    ///* `format` a [Template](crate::utils::format)
+    ///  * `{capacity}` or `%c` the charge percentage
+    ///  * `{icon}` or `%i` the correct icon from `icons`
+    ///  * `{charging}` true while the device is charging
+    ///  * `{status}` Charging, Discharging, Full, Not charging or Unknown
+    ///  * `{time_to_empty}` and `{time_to_full}` the estimated time as `h:mm`, empty if unknown
+    ///  * `{time}` the one of the two that applies
+    ///  * `{power}` the power drawn or received, in watts
+    ///  * `{health}` the full capacity as a percentage of the design capacity
+    ///* `power_supply` the [PowerSupply] of the batteries
     ///* `icons` sets a custom [BatteryIcons]
     ///* `config` a [&WidgetConfig]
     pub async fn new(
         format: impl ToString,
+        power_supply: PowerSupply,
         icons: Option<BatteryIcons>,
         config: &WidgetConfig,
         low_battery_warning: impl LowBatteryWarner + 'static,
     ) -> Result<Box<Self>> {
-        let mut root_path = String::default();
-        for path in read_dir("/sys/class/power_supply")
-            .map_err(Error::from)?
-            .flatten()
-        {
-            let name = path.path().to_string_lossy().to_string();
-            if name.contains("BAT") {
-                root_path.clone_from(&name);
-                break;
-            }
-        }
-        if root_path.is_empty() {
+        if power_supply.batteries()?.is_empty() {
             return Err(Error::NoBattery.into());
         }
 
         Ok(Box::new(Self {
-            format: format.to_string(),
+            format: Template::with_aliases(&format.to_string(), Self::ALIASES),
             inner: *Text::new("", config).await,
-            root_path,
+            update_interval: config.update_interval,
+            power_supply,
             icons: icons.unwrap_or_default(),
             low_battery_warning: Box::new(low_battery_warning),
+            status: None,
         }))
     }
-
-    fn read_os_file(&self, filename: &str) -> Option<String> {
-        let path = format!("{}/{}", self.root_path, filename);
-        let value = std::fs::read_to_string(path).ok()?;
-        Some(value.trim().into())
-    }
-
-    fn get_charge(&self) -> Option<f64> {
-        self.percentage_from_files("charge_now", "charge_full")
-    }
-
-    fn get_energy(&self) -> Option<f64> {
-        self.percentage_from_files("energy_now", "energy_full")
-    }
-
-    fn percentage_from_files(&self, f1: &str, f2: &str) -> Option<f64> {
-        let v1 = self.read_os_file(f1)?.parse::<f64>().ok()?;
-        let v2 = self.read_os_file(f2)?.parse::<f64>().ok()?;
-        Some(v1 / v2 * 100.0)
-    }
 }
 
 #[async_trait]
 impl Widget for Battery {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating battery");
-        let percent = match (self.get_charge(), self.get_energy()) {
-            (Some(c), Some(_)) => c,
-            (Some(c), None) => c,
-            (None, Some(e)) => e,
-            (None, None) => return Ok(()),
+        let Some(status) = self.power_supply.status()? else {
+            return Ok(false);
         };
+        let percent = status.capacity;
+        let is_charging = status.state == ChargeState::Charging;
 
-        let is_charging = self.read_os_file("status") == Some("Charging".into());
-
-        if self.low_battery_warning.should_warn(percent, is_charging) {
-            let f = self.low_battery_warning.warn(percent);
-            f.await;
-        }
+        self.low_battery_warning.check(&status).await;
 
         let percentages = if is_charging {
             &self.icons.percentages_charging
@@ -131,20 +118,57 @@ impl Widget for Battery {
             &percentages[index]
         };
 
-        let text = self
-            .format
-            .replace("%i", icon)
-            .replace("%c", &percent.round().to_string());
-        self.inner.set_text(text);
-        Ok(())
+        let optional = |value: Option<f64>| value.map_or(Value::from(""), Value::from);
+        let time_to_empty = status.time_to_empty.map(format_duration);
+        let time_to_full = status.time_to_full.map(format_duration);
+        let values = [
+            ("capacity", percent.round().into()),
+            ("icon", icon.as_str().into()),
+            ("charging", is_charging.into()),
+            ("status", status.state.to_string().into()),
+            (
+                "time",
+                time_to_full
+                    .clone()
+                    .or(time_to_empty.clone())
+                    .unwrap_or_default()
+                    .into(),
+            ),
+            ("time_to_empty", time_to_empty.unwrap_or_default().into()),
+            ("time_to_full", time_to_full.unwrap_or_default().into()),
+            ("power", optional(status.power)),
+            ("health", optional(status.health)),
+        ];
+        let changed = self.inner.set_level(percent) | self.inner.render(&self.format, &values);
+        self.status = Some(status);
+        Ok(changed)
     }
 
     async fn hook(&mut self, sender: HookSender, timed_hooks: &mut TimedHooks) -> Result<()> {
-        timed_hooks.subscribe(sender);
+        timed_hooks.subscribe(self.update_interval, sender);
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    /// The state, the time remaining, the power and the health
+    fn tooltip(&self) -> Option<String> {
+        let status = self.status.as_ref()?;
+        let mut lines = vec![format!("<b>{:.0}% {}</b>", status.capacity, status.state)];
+        if let Some(time) = status.time_to_empty {
+            lines.push(format!("Time to empty: {}", format_duration(time)));
+        }
+        if let Some(time) = status.time_to_full {
+            lines.push(format!("Time to full: {}", format_duration(time)));
+        }
+        if let Some(power) = status.power {
+            lines.push(format!("Power: {:.1} W", power));
+        }
+        if let Some(health) = status.health {
+            lines.push(format!("Health: {:.0}%", health));
+        }
+        Some(lines.join("\n"))
+    }
+
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for Battery {
@@ -153,67 +177,501 @@ impl Display for Battery {
     }
 }
 
+/// Formats `duration` as `h:mm`
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Which batteries a [PowerSupply] reads
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BatterySelection {
+    /// all the system batteries, combined
+    #[default]
+    All,
+    /// the battery with this name, e.g. `BAT1`
+    Named(String),
+}
+
+/// Charging state reported by the kernel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChargeState {
+    Charging,
+    Discharging,
+    Full,
+    NotCharging,
+    Unknown,
+}
+
+impl ChargeState {
+    fn parse(status: &str) -> Self {
+        match status {
+            "Charging" => Self::Charging,
+            "Discharging" => Self::Discharging,
+            "Full" => Self::Full,
+            "Not charging" => Self::NotCharging,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl Display for ChargeState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            Self::Charging => "Charging",
+            Self::Discharging => "Discharging",
+            Self::Full => "Full",
+            Self::NotCharging => "Not charging",
+            Self::Unknown => "Unknown",
+        };
+        status.fmt(f)
+    }
+}
+
+/// State of the selected batteries, combined by energy
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryStatus {
+    /// in a range from 0 to 100
+    pub capacity: f64,
+    pub state: ChargeState,
+    pub time_to_empty: Option<Duration>,
+    pub time_to_full: Option<Duration>,
+    /// in watts
+    pub power: Option<f64>,
+    /// full capacity as a percentage of the design capacity
+    pub health: Option<f64>,
+}
+
+/// Values of a single battery, in watt-hours and watts
+#[derive(Debug, Clone, Copy)]
+struct Reading {
+    now: f64,
+    full: f64,
+    full_design: Option<f64>,
+    power: Option<f64>,
+    state: ChargeState,
+    /// false if the battery reports its charge without a voltage,
+    /// the values are then in amp-hours and amps
+    energy: bool,
+}
+
+/// Reads the batteries from sysfs
+#[derive(Debug, Clone)]
+pub struct PowerSupply {
+    root: PathBuf,
+    selection: BatterySelection,
+}
+
+impl Default for PowerSupply {
+    fn default() -> Self {
+        Self::new(BatterySelection::All)
+    }
+}
+
+impl PowerSupply {
+    pub fn new(selection: BatterySelection) -> Self {
+        Self::with_root("/sys/class/power_supply", selection)
+    }
+
+    /// Reads the batteries in `root` instead of `/sys/class/power_supply`
+    pub fn with_root(root: impl Into<PathBuf>, selection: BatterySelection) -> Self {
+        Self {
+            root: root.into(),
+            selection,
+        }
+    }
+
+    /// Folders of the selected batteries, the batteries of devices like mice are ignored
+    pub fn batteries(&self) -> Result<Vec<PathBuf>> {
+        let mut batteries = Vec::new();
+        for entry in read_dir(&self.root).map_err(Error::from)?.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let selected = match &self.selection {
+                BatterySelection::All => {
+                    let is_battery = match read_value(&path, "type") {
+                        Some(kind) => kind == "Battery",
+                        None => name.contains("BAT"),
+                    };
+                    is_battery && read_value(&path, "scope").as_deref() != Some("Device")
+                }
+                BatterySelection::Named(selected) => name == *selected,
+            };
+            if selected {
+                batteries.push(path);
+            }
+        }
+        batteries.sort();
+        Ok(batteries)
+    }
+
+    /// `None` if none of the batteries can be read
+    pub fn status(&self) -> Result<Option<BatteryStatus>> {
+        let mut readings: Vec<Reading> = self
+            .batteries()?
+            .iter()
+            .filter_map(|path| read_battery(path))
+            .filter(|reading| reading.full > 0.0)
+            .collect();
+        // amp-hours can't be added to watt-hours
+        if readings.iter().any(|r| r.energy) {
+            readings.retain(|r| r.energy);
+        }
+        if readings.is_empty() {
+            return Ok(None);
+        }
+
+        let now: f64 = readings.iter().map(|r| r.now).sum();
+        let full: f64 = readings.iter().map(|r| r.full).sum();
+        let state = combined_state(&readings);
+        let power = readings
+            .iter()
+            .map(|r| r.power)
+            .sum::<Option<f64>>()
+            .filter(|power| *power > 0.0);
+        let hours =
+            |energy: f64| power.map(|power| Duration::from_secs_f64(energy / power * 3600.0));
+        let health = readings
+            .iter()
+            .map(|r| r.full_design)
+            .sum::<Option<f64>>()
+            .filter(|design| *design > 0.0)
+            .map(|design| full / design * 100.0);
+
+        let (time_to_empty, time_to_full) = match state {
+            ChargeState::Discharging => (hours(now), None),
+            ChargeState::Charging => (None, hours((full - now).max(0.0))),
+            _ => (None, None),
+        };
+
+        Ok(Some(BatteryStatus {
+            capacity: (now / full * 100.0).clamp(0.0, 100.0),
+            state,
+            time_to_empty,
+            time_to_full,
+            // in amps without a voltage
+            power: power.filter(|_| readings[0].energy),
+            health,
+        }))
+    }
+}
+
+/// Charging if any battery is charging, discharging if any is discharging
+fn combined_state(readings: &[Reading]) -> ChargeState {
+    let any = |state| readings.iter().any(|r| r.state == state);
+    if any(ChargeState::Charging) {
+        ChargeState::Charging
+    } else if any(ChargeState::Discharging) {
+        ChargeState::Discharging
+    } else if readings.iter().all(|r| r.state == ChargeState::Full) {
+        ChargeState::Full
+    } else {
+        readings[0].state
+    }
+}
+
+fn read_value(path: &Path, file: &str) -> Option<String> {
+    let value = read_to_string(path.join(file)).ok()?;
+    Some(value.trim().into())
+}
+
+fn read_number(path: &Path, file: &str) -> Option<f64> {
+    read_value(path, file)?.parse().ok()
+}
+
+/// Batteries report either energy in µWh and power in µW or charge in µAh and current in µA,
+/// the charge is converted to energy with the voltage to combine different batteries
+fn read_battery(path: &Path) -> Option<Reading> {
+    let state = read_value(path, "status").map_or(ChargeState::Unknown, |s| ChargeState::parse(&s));
+    let micro = |value: f64| value / 1_000_000.0;
+    if let (Some(now), Some(full)) = (
+        read_number(path, "energy_now"),
+        read_number(path, "energy_full"),
+    ) {
+        return Some(Reading {
+            now: micro(now),
+            full: micro(full),
+            full_design: read_number(path, "energy_full_design").map(micro),
+            // negative while discharging on some laptops
+            power: read_number(path, "power_now").map(|power| micro(power.abs())),
+            state,
+            energy: true,
+        });
+    }
+
+    let now = read_number(path, "charge_now")?;
+    let full = read_number(path, "charge_full")?;
+    let voltage = read_number(path, "voltage_now")
+        .or_else(|| read_number(path, "voltage_min_design"))
+        .map(micro);
+    let has_voltage = voltage.is_some();
+    let voltage = voltage.unwrap_or(1.0);
+    let energy = |charge: f64| micro(charge) * voltage;
+    Some(Reading {
+        now: energy(now),
+        full: energy(full),
+        full_design: read_number(path, "charge_full_design").map(energy),
+        power: read_number(path, "current_now").map(|current| energy(current.abs())),
+        state,
+        energy: has_voltage,
+    })
+}
+
 #[async_trait]
 pub trait LowBatteryWarner: Send + std::fmt::Debug {
-    fn should_warn(&mut self, charge: f64, is_charging: bool) -> bool;
-    async fn warn(&self, charge: f64);
+    /// Called on every update with the state of the batteries,
+    /// failures are logged by the warner instead of making the widget fail
+    async fn check(&mut self, status: &BatteryStatus);
 }
 
-#[derive(Debug)]
-pub struct NotifySend {
-    warn_time_20: Option<Instant>,
-    warn_time_5: Option<Instant>,
+/// Done when a [Threshold] is crossed, after the notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatteryAction {
+    /// a shell command
+    Command(String),
+    /// suspends the system through logind
+    Suspend,
+    /// hibernates the system through logind
+    Hibernate,
 }
 
-#[async_trait]
-impl LowBatteryWarner for NotifySend {
-    fn should_warn(&mut self, charge: f64, is_charging: bool) -> bool {
-        if is_charging {
-            return false;
+impl BatteryAction {
+    async fn run(&self) -> Result<()> {
+        let method = match self {
+            Self::Command(command) => {
+                // not awaited, the command can take as long as it needs
+                tokio::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .spawn()
+                    .map_err(Error::from)?;
+                return Ok(());
+            }
+            Self::Suspend => "Suspend",
+            Self::Hibernate => "Hibernate",
+        };
+        let connection = Connection::system().await.map_err(Error::from)?;
+        connection
+            .call_method(
+                Some("org.freedesktop.login1"),
+                "/org/freedesktop/login1",
+                Some("org.freedesktop.login1.Manager"),
+                method,
+                // not interactive, there is no one to ask for a password
+                &(false,),
+            )
+            .await
+            .map_err(Error::from)?;
+        Ok(())
+    }
+}
+
+/// A warning shown while the batteries discharge below a charge
+#[derive(Debug, Clone)]
+pub struct Threshold {
+    below: f64,
+    urgency: Urgency,
+    message: Template,
+    repeat: Option<Duration>,
+    action: Option<BatteryAction>,
+    last_warning: Option<Instant>,
+}
+
+impl Threshold {
+    /// Warns once when the charge drops below `below` percent
+    pub fn new(below: f64) -> Self {
+        Self {
+            below,
+            urgency: Urgency::default(),
+            message: Template::parse("Battery is low: {capacity:.1}% left"),
+            repeat: None,
+            action: None,
+            last_warning: None,
         }
+    }
 
-        const FIVE_MINUTES: Duration = Duration::from_secs(60 * 5);
-        if charge < 20.0
-            && self
-                .warn_time_20
-                .map(|d| d.elapsed() > FIVE_MINUTES)
-                .unwrap_or(true)
-        {
-            self.warn_time_20 = Some(Instant::now());
-            return true;
-        }
-
-        if charge < 5.0
-            && self
-                .warn_time_5
-                .map(|d| d.elapsed() > FIVE_MINUTES)
-                .unwrap_or(true)
-        {
-            self.warn_time_5 = Some(Instant::now());
-            return true;
-        }
-
-        false
-    }
-
-    async fn warn(&self, charge: f64) {
-        let body = format!("Battery is low: {:.1}% left", charge);
-        let n = libnotify::Notification::new("Low battery", Some(body.as_ref()), None);
-        n.set_urgency(if charge < 5.0 {
-            libnotify::Urgency::Critical
-        } else {
-            libnotify::Urgency::Normal
-        });
-        n.show().expect("failed to show notification");
+    pub fn urgency(mut self, urgency: Urgency) -> Self {
+        self.urgency = urgency;
+        self
+    }
+
+    /// A [Template](crate::utils::format) with the placeholders `{capacity}`,
+    /// `{time}` the estimated time to empty and `{power}`
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = Template::parse(message);
+        self
+    }
+
+    /// Warns again after `repeat` while the charge stays below the threshold
+    pub fn repeat(mut self, repeat: Duration) -> Self {
+        self.repeat = Some(repeat);
+        self
+    }
+
+    pub fn action(mut self, action: BatteryAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    fn is_due(&self) -> bool {
+        match (self.last_warning, self.repeat) {
+            (None, _) => true,
+            (Some(last), Some(repeat)) => last.elapsed() >= repeat,
+            (Some(_), None) => false,
+        }
     }
 }
 
-impl Default for NotifySend {
+/// Notifies when the batteries cross a [Threshold] and, optionally,
+/// when the charger is connected or disconnected
+#[derive(Debug, Clone)]
+pub struct ThresholdWarner {
+    /// sorted by charge, the lowest first
+    thresholds: Vec<Threshold>,
+    charger_notifications: bool,
+    previous_state: Option<ChargeState>,
+    /// connected at the first notification
+    notifier: Option<Notifier>,
+    /// the last notification, replaced by the next one
+    notification_id: Option<u32>,
+}
+
+/// The warner used before [ThresholdWarner], warns below 20% and 5% every five minutes
+pub type NotifySend = ThresholdWarner;
+
+impl Default for ThresholdWarner {
     fn default() -> Self {
-        libnotify::init("barust").expect("libnotify init failed");
+        const FIVE_MINUTES: Duration = Duration::from_secs(60 * 5);
+        Self::new()
+            .threshold(Threshold::new(20.0).repeat(FIVE_MINUTES))
+            .threshold(
+                Threshold::new(5.0)
+                    .urgency(Urgency::Critical)
+                    .repeat(FIVE_MINUTES),
+            )
+    }
+}
+
+impl ThresholdWarner {
+    /// A warner without thresholds
+    pub fn new() -> Self {
         Self {
-            warn_time_20: None,
-            warn_time_5: None,
+            thresholds: Vec::new(),
+            charger_notifications: false,
+            previous_state: None,
+            notifier: None,
+            notification_id: None,
+        }
+    }
+
+    pub fn threshold(mut self, threshold: Threshold) -> Self {
+        let index = self
+            .thresholds
+            .partition_point(|t| t.below <= threshold.below);
+        self.thresholds.insert(index, threshold);
+        self
+    }
+
+    /// Notifies when the charger is connected or disconnected
+    pub fn charger_notifications(mut self, enabled: bool) -> Self {
+        self.charger_notifications = enabled;
+        self
+    }
+
+    async fn notify(&mut self, summary: &str, body: &str, urgency: Urgency) -> Result<()> {
+        let notifier = match &self.notifier {
+            Some(notifier) => notifier,
+            None => self
+                .notifier
+                .insert(Notifier::new().await.map_err(Error::from)?),
+        };
+        let mut notification = Notification::new(summary)
+            .body(body)
+            .icon("battery")
+            .urgency(urgency);
+        if let Some(id) = self.notification_id {
+            notification = notification.replaces(id);
+        }
+        let id = notifier.send(&notification).await.map_err(Error::from)?;
+        self.notification_id = Some(id);
+        Ok(())
+    }
+
+    async fn check_charger(&mut self, status: &BatteryStatus) {
+        let previous = self.previous_state.replace(status.state);
+        if !self.charger_notifications {
+            return;
+        }
+        let was_discharging = match previous {
+            None | Some(ChargeState::Unknown) => return,
+            Some(state) => state == ChargeState::Discharging,
+        };
+        let is_discharging = match status.state {
+            ChargeState::Unknown => return,
+            state => state == ChargeState::Discharging,
+        };
+        let summary = match (was_discharging, is_discharging) {
+            (true, false) => "Charger connected",
+            (false, true) => "Charger disconnected",
+            _ => return,
+        };
+        let body = format!("Battery at {:.0}%", status.capacity);
+        if let Err(e) = self.notify(summary, &body, Urgency::Low).await {
+            warn!("Cannot send the charger notification: {}", e);
+        }
+    }
+}
+
+#[async_trait]
+impl LowBatteryWarner for ThresholdWarner {
+    async fn check(&mut self, status: &BatteryStatus) {
+        self.check_charger(status).await;
+
+        if status.state != ChargeState::Discharging {
+            // warns again at the next discharge
+            for threshold in &mut self.thresholds {
+                threshold.last_warning = None;
+            }
+            return;
+        }
+
+        // only the most severe threshold crossed
+        let Some(threshold) = self
+            .thresholds
+            .iter_mut()
+            .find(|t| status.capacity < t.below)
+        else {
+            return;
+        };
+        if !threshold.is_due() {
+            return;
+        }
+        // set first, a failing notification is not retried on every update
+        threshold.last_warning = Some(Instant::now());
+
+        let values = [
+            ("capacity", status.capacity.into()),
+            (
+                "time",
+                status
+                    .time_to_empty
+                    .map(format_duration)
+                    .unwrap_or_default()
+                    .into(),
+            ),
+            ("power", status.power.unwrap_or_default().into()),
+        ];
+        let body = threshold.message.render(&values);
+        let urgency = threshold.urgency;
+        let action = threshold.action.clone();
+        // the action doesn't depend on a notification daemon
+        if let Err(e) = self.notify("Low battery", &body, urgency).await {
+            warn!("Cannot send the low battery notification: {}", e);
+        }
+        if let Some(action) = action {
+            if let Err(e) = action.run().await {
+                error!("Cannot run the low battery action {:?}: {}", action, e);
+            }
         }
     }
 }
@@ -224,4 +682,207 @@ pub enum Error {
     IO(#[from] std::io::Error),
     #[error("No battery found")]
     NoBattery,
+    Zbus(#[from] zbus::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir, write};
+    use tempfile::TempDir;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    /// Adds a power supply called `name` to `root`
+    fn supply(root: &TempDir, name: &str, files: &[(&str, &str)]) {
+        let path = root.path().join(name);
+        create_dir(&path).unwrap();
+        for (file, value) in files {
+            write(path.join(file), format!("{}\n", value)).unwrap();
+        }
+    }
+
+    fn status(root: &TempDir) -> BatteryStatus {
+        PowerSupply::with_root(root.path(), BatterySelection::All)
+            .status()
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn energy_battery() {
+        let root = TempDir::new().unwrap();
+        supply(
+            &root,
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Discharging"),
+                ("energy_now", "30000000"),
+                ("energy_full", "60000000"),
+                ("energy_full_design", "80000000"),
+                ("power_now", "15000000"),
+            ],
+        );
+        let status = status(&root);
+        assert_eq!(status.capacity, 50.0);
+        assert_eq!(status.state, ChargeState::Discharging);
+        assert_eq!(status.time_to_empty, Some(2 * HOUR));
+        assert_eq!(status.time_to_full, None);
+        assert_eq!(status.power, Some(15.0));
+        assert_eq!(status.health, Some(75.0));
+    }
+
+    #[test]
+    fn charge_battery() {
+        let root = TempDir::new().unwrap();
+        supply(
+            &root,
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Charging"),
+                ("charge_now", "2000000"),
+                ("charge_full", "4000000"),
+                ("charge_full_design", "5000000"),
+                ("current_now", "1000000"),
+                ("voltage_now", "12000000"),
+            ],
+        );
+        let status = status(&root);
+        assert_eq!(status.capacity, 50.0);
+        assert_eq!(status.state, ChargeState::Charging);
+        assert_eq!(status.time_to_empty, None);
+        assert_eq!(status.time_to_full, Some(2 * HOUR));
+        assert_eq!(status.power, Some(12.0));
+        assert_eq!(status.health, Some(80.0));
+    }
+
+    #[test]
+    fn charge_battery_without_voltage() {
+        let root = TempDir::new().unwrap();
+        supply(
+            &root,
+            "BAT0",
+            &[
+                ("status", "Discharging"),
+                ("charge_now", "1000000"),
+                ("charge_full", "4000000"),
+                ("current_now", "-1000000"),
+            ],
+        );
+        let status = status(&root);
+        assert_eq!(status.capacity, 25.0);
+        assert_eq!(status.time_to_empty, Some(HOUR));
+        // the current is not a power
+        assert_eq!(status.power, None);
+    }
+
+    #[test]
+    fn batteries_are_combined() {
+        let root = TempDir::new().unwrap();
+        supply(
+            &root,
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Discharging"),
+                ("energy_now", "10000000"),
+                ("energy_full", "40000000"),
+                ("energy_full_design", "50000000"),
+                ("power_now", "10000000"),
+            ],
+        );
+        supply(
+            &root,
+            "BAT1",
+            &[
+                ("type", "Battery"),
+                ("status", "Not charging"),
+                ("energy_now", "30000000"),
+                ("energy_full", "40000000"),
+                ("energy_full_design", "50000000"),
+                ("power_now", "0"),
+            ],
+        );
+        let status = status(&root);
+        assert_eq!(status.capacity, 50.0);
+        assert_eq!(status.state, ChargeState::Discharging);
+        assert_eq!(status.time_to_empty, Some(4 * HOUR));
+        assert_eq!(status.power, Some(10.0));
+        assert_eq!(status.health, Some(80.0));
+
+        let named = PowerSupply::with_root(root.path(), BatterySelection::Named("BAT1".into()));
+        let status = named.status().unwrap().unwrap();
+        assert_eq!(status.capacity, 75.0);
+        assert_eq!(status.state, ChargeState::NotCharging);
+    }
+
+    #[test]
+    fn charge_without_voltage_is_not_added_to_energy() {
+        let root = TempDir::new().unwrap();
+        supply(
+            &root,
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Full"),
+                ("energy_now", "20000000"),
+                ("energy_full", "40000000"),
+            ],
+        );
+        supply(
+            &root,
+            "BAT1",
+            &[
+                ("type", "Battery"),
+                ("status", "Full"),
+                ("charge_now", "1000000"),
+                ("charge_full", "4000000"),
+            ],
+        );
+        let status = status(&root);
+        assert_eq!(status.capacity, 50.0);
+        assert_eq!(status.state, ChargeState::Full);
+        assert_eq!((status.time_to_empty, status.time_to_full), (None, None));
+    }
+
+    #[test]
+    fn device_batteries_are_excluded() {
+        let root = TempDir::new().unwrap();
+        supply(
+            &root,
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Discharging"),
+                ("energy_now", "50000000"),
+                ("energy_full", "100000000"),
+            ],
+        );
+        supply(
+            &root,
+            "hidpp_battery_0",
+            &[
+                ("type", "Battery"),
+                ("scope", "Device"),
+                ("status", "Discharging"),
+                ("energy_now", "1000000"),
+                ("energy_full", "100000000"),
+            ],
+        );
+        supply(&root, "AC", &[("type", "Mains"), ("online", "0")]);
+
+        let supply = PowerSupply::with_root(root.path(), BatterySelection::All);
+        assert_eq!(supply.batteries().unwrap(), vec![root.path().join("BAT0")]);
+        assert_eq!(status(&root).capacity, 50.0);
+    }
+
+    #[test]
+    fn no_battery() {
+        let root = TempDir::new().unwrap();
+        supply(&root, "AC", &[("type", "Mains"), ("online", "1")]);
+        let supply = PowerSupply::with_root(root.path(), BatterySelection::All);
+        assert_eq!(supply.status().unwrap(), None);
+    }
 }
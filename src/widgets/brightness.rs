@@ -1,16 +1,27 @@
 use crate::{
-    utils::{percentage_to_index, HookSender, ResettableTimer, TimedHooks},
+    backend::Osd,
+    utils::{
+        percentage_to_index, HookSender, MouseButton, ResettableTimer, StatusBarInfo, Template,
+        TimedHooks,
+    },
     widget_default,
     widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
 };
 use async_trait::async_trait;
 use cairo::Context;
-use std::{fmt::Display, fs, io::SeekFrom, ops::DerefMut, path::PathBuf, process::Command};
+use inotify::{Inotify, WatchMask};
+use log::{debug, warn};
+use std::{fmt::Display, fs, io::SeekFrom, ops::DerefMut, path::PathBuf, thread, time::Duration};
 use tokio::{
     fs::File,
     io::{AsyncReadExt, AsyncSeekExt},
-    sync::Mutex,
+    process::Command,
+    sync::{Mutex, OnceCell},
 };
+use zbus::Connection;
+
+/// Brightness change of a scroll step, in percent
+const SCROLL_STEP: f64 = 5.0;
 
 /// Icons used by [Brightness]
 #[derive(Debug)]
@@ -29,20 +40,32 @@ impl Default for BrightnessIcons {
 
 #[derive(Debug)]
 pub struct Brightness {
-    format: String,
+    format: Template,
     brightness_provider: Box<dyn BrightnessProvider>,
-    previous_brightness: f64,
+    /// `None` until the first update
+    previous_brightness: Option<f64>,
     show_counter: ResettableTimer,
     inner: Text,
+    update_interval: Duration,
     icons: BrightnessIcons,
+    sender: Option<HookSender>,
+    /// used to create the [Osd] if it is enabled
+    osd_config: Option<WidgetConfig>,
+    osd: Option<Osd>,
 }
 
 impl Brightness {
-    ///* `format`
-    ///  * *%p* will be replaced with the brightness percentage
-    ///  * *%i* will be replaced with the correct icon
-    ///* `brightness_command` a function that returns the brightness in a range from 0 to 100
-    ///* `icons` sets a custom [VolumeIcons]
+    /// Format used when the config file doesn't set one
+    pub const DEFAULT_FORMAT: &'static str = "{icon} {percent:.0}%";
+    /// Legacy `%x` tokens of the format
+    pub(crate) const ALIASES: &'static [(&'static str, &'static str)] =
+        &[("%p", "{percent:.0}"), ("%i", "{icon}")];
+
+    ///* `format` a [Template](crate::utils::format)
+    ///  * *{percent}* or *%p* the brightness percentage
+    ///  * *{icon}* or *%i* the correct icon
+    ///* `brightness_provider` a [BrightnessProvider]
+    ///* `icons` sets a custom [BrightnessIcons]
     ///* `config` a [&WidgetConfig]
     pub async fn new(
         format: impl ToString,
@@ -51,49 +74,90 @@ impl Brightness {
         config: &WidgetConfig,
     ) -> Box<Self> {
         Box::new(Self {
-            format: format.to_string(),
-            previous_brightness: 0.0,
+            format: Template::with_aliases(&format.to_string(), Self::ALIASES),
+            previous_brightness: None,
             brightness_provider,
             show_counter: ResettableTimer::new(config.hide_timeout),
             inner: *Text::new("", config).await,
+            update_interval: config.update_interval,
             icons: icons.unwrap_or_default(),
+            sender: None,
+            osd_config: config.osd.then(|| config.clone()),
+            osd: None,
         })
     }
 
-    fn build_string(&self, current_brightness: f64) -> String {
+    fn icon(&self, brightness: f64) -> &str {
+        let percentages_len = self.icons.percentages.len();
+        let index = percentage_to_index(brightness, (0, percentages_len - 1));
+        &self.icons.percentages[index]
+    }
+
+    /// Returns true if the text changed
+    fn render(&mut self, current_brightness: f64) -> bool {
         if self.show_counter.is_done() {
-            return String::from("");
+            return self.inner.set_text("");
         }
-        let percentages_len = self.icons.percentages.len();
-        let index = percentage_to_index(current_brightness, (0, percentages_len - 1));
-        self.format
-            .replace("%p", &format!("{:.0}", current_brightness))
-            .replace("%i", &self.icons.percentages[index].to_string())
+        let values = [
+            ("percent", current_brightness.into()),
+            ("icon", self.icon(current_brightness).into()),
+        ];
+        self.inner.render(&self.format, &values)
     }
 }
 
 #[async_trait]
 impl Widget for Brightness {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         let f = self.brightness_provider.brightness();
         let current_brightness = f.await.ok_or(Error::Command)?;
 
-        if current_brightness != self.previous_brightness {
-            self.previous_brightness = current_brightness;
-            self.show_counter.reset();
+        if self.previous_brightness != Some(current_brightness) {
+            // the initial brightness is not a change
+            if self
+                .previous_brightness
+                .replace(current_brightness)
+                .is_some()
+            {
+                if let Some(osd) = &self.osd {
+                    osd.show(self.icon(current_brightness), current_brightness);
+                }
+            }
+            // inotify doesn't report the end of the hide timeout
+            self.show_counter.reset_and_notify(self.sender.clone());
         }
-        let text = self.build_string(current_brightness);
-        self.inner.set_text(text);
+        Ok(self.render(current_brightness))
+    }
 
+    fn setup(&mut self, info: &StatusBarInfo) -> Result<()> {
+        if let Some(config) = &self.osd_config {
+            self.osd = Osd::for_bar(info, config);
+        }
         Ok(())
     }
 
     async fn hook(&mut self, sender: HookSender, timed_hooks: &mut TimedHooks) -> Result<()> {
-        timed_hooks.subscribe(sender);
+        if self.brightness_provider.subscribe(sender.clone()) {
+            self.sender = Some(sender);
+        } else {
+            timed_hooks.subscribe(self.update_interval, sender);
+        }
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    /// Scrolling changes the brightness
+    async fn on_click(&mut self, button: MouseButton, _x: u32, _y: u32) -> Result<()> {
+        let delta = match button {
+            MouseButton::ScrollUp => SCROLL_STEP,
+            MouseButton::ScrollDown => -SCROLL_STEP,
+            _ => return Ok(()),
+        };
+        let f = self.brightness_provider.change_brightness(delta);
+        f.await.ok_or(Error::Control)?;
+        Ok(())
+    }
+
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for Brightness {
@@ -110,11 +174,29 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error("Failed to find a valid sysfs folder")]
     NoBrightnessFile,
+    #[error("Cannot change the brightness")]
+    Control,
 }
 
 #[async_trait]
-pub trait BrightnessProvider: std::fmt::Debug + Send {
+pub trait BrightnessProvider: std::fmt::Debug + Send + Sync {
+    /// The brightness in a range from 0 to 100
     async fn brightness(&self) -> Option<f64>;
+    /// Sets the brightness to `percent`, in a range from 0 to 100
+    async fn set_brightness(&self, _percent: f64) -> Option<()> {
+        None
+    }
+    /// Adds `delta` percent to the brightness
+    async fn change_brightness(&self, delta: f64) -> Option<()> {
+        let brightness = self.brightness().await?;
+        self.set_brightness((brightness + delta).clamp(0.0, 100.0))
+            .await
+    }
+    /// Asks `sender` for an update whenever the brightness changes,
+    /// returns false if the provider has to be polled instead
+    fn subscribe(&mut self, _sender: HookSender) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Default)]
@@ -129,18 +211,103 @@ impl LightProvider {
 #[async_trait]
 impl BrightnessProvider for LightProvider {
     async fn brightness(&self) -> Option<f64> {
-        String::from_utf8(Command::new("light").output().ok()?.stdout)
+        String::from_utf8(Command::new("light").output().await.ok()?.stdout)
             .ok()?
             .trim()
             .parse::<f64>()
             .ok()
     }
+
+    async fn set_brightness(&self, percent: f64) -> Option<()> {
+        let status = Command::new("light")
+            .arg("-S")
+            .arg(percent.to_string())
+            .status()
+            .await
+            .ok()?;
+        status.success().then_some(())
+    }
+}
+
+/// Uses [brightnessctl](https://github.com/Hummer12007/brightnessctl)
+#[derive(Debug, Default)]
+pub struct BrightnessctlProvider {
+    device: Option<String>,
+}
+
+impl BrightnessctlProvider {
+    /// Controls the first backlight device
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Controls the device called `device`, e.g. `intel_backlight`
+    pub fn with_device(device: impl ToString) -> Self {
+        Self {
+            device: Some(device.to_string()),
+        }
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new("brightnessctl");
+        match &self.device {
+            Some(device) => command.arg("--device").arg(device),
+            None => command.arg("--class").arg("backlight"),
+        };
+        command
+    }
+
+    /// `value` follows the syntax of `brightnessctl set`, e.g. `50%` or `5%+`
+    async fn set(&self, value: String) -> Option<()> {
+        let mut command = self.command();
+        let status = command
+            .arg("--quiet")
+            .arg("set")
+            .arg(value)
+            .status()
+            .await
+            .ok()?;
+        status.success().then_some(())
+    }
+}
+
+#[async_trait]
+impl BrightnessProvider for BrightnessctlProvider {
+    async fn brightness(&self) -> Option<f64> {
+        // device,class,current,percentage,max
+        let output = self
+            .command()
+            .arg("--machine-readable")
+            .output()
+            .await
+            .ok()?;
+        let output = String::from_utf8(output.stdout).ok()?;
+        let mut fields = output.lines().next()?.split(',').skip(2);
+        let current = fields.next()?.parse::<f64>().ok()?;
+        let max = fields.nth(1)?.parse::<f64>().ok()?;
+        Some(current / max * 100.0)
+    }
+
+    async fn set_brightness(&self, percent: f64) -> Option<()> {
+        self.set(format!("{}%", percent.clamp(0.0, 100.0).round()))
+            .await
+    }
+
+    async fn change_brightness(&self, delta: f64) -> Option<()> {
+        let sign = if delta < 0.0 { '-' } else { '+' };
+        self.set(format!("{}%{}", delta.abs().round(), sign)).await
+    }
 }
 
+/// Reads the brightness from `/sys/class/backlight`,
+/// sets it through logind so that root is not needed
 #[derive(Debug)]
 pub struct SysfsProvider {
+    device: String,
+    brightness_path: PathBuf,
     brightness_file: Mutex<File>,
     max_brightness_file: Mutex<File>,
+    connection: OnceCell<Connection>,
 }
 
 impl SysfsProvider {
@@ -154,10 +321,17 @@ impl SysfsProvider {
         folder.push(device.file_name());
 
         let mut brightness = None;
+        let mut actual_brightness = None;
         let mut max_brightness = None;
         let mut d = fs::read_dir(folder).map_err(Error::from)?;
         while let Some(Ok(file)) = d.next() {
             match file.file_name().to_str() {
+                // the brightness set by the hardware, which can differ from the requested one
+                Some("actual_brightness") => {
+                    let mut path = device.path();
+                    path.push("actual_brightness");
+                    actual_brightness = Some(path)
+                }
                 Some("brightness") => {
                     let mut path = device.path();
                     path.push("brightness");
@@ -171,15 +345,20 @@ impl SysfsProvider {
                 _ => (),
             }
         }
-        let brightness_path = brightness.ok_or(Error::NoBrightnessFile)?;
+        let brightness_path = actual_brightness
+            .or(brightness)
+            .ok_or(Error::NoBrightnessFile)?;
         let max_brightness_path = max_brightness.ok_or(Error::NoBrightnessFile)?;
         let brightness_file = File::open(&brightness_path).await.map_err(Error::from)?;
         let max_brightness_file = File::open(&max_brightness_path)
             .await
             .map_err(Error::from)?;
         Ok(Self {
+            device: device.file_name().to_string_lossy().into_owned(),
+            brightness_path,
             brightness_file: Mutex::new(brightness_file),
             max_brightness_file: Mutex::new(max_brightness_file),
+            connection: OnceCell::new(),
         })
     }
 
@@ -214,4 +393,55 @@ impl BrightnessProvider for SysfsProvider {
     async fn brightness(&self) -> Option<f64> {
         Some(self.read_brightness_raw().await? / self.read_max_brightness_raw().await? * 100.0)
     }
+
+    async fn set_brightness(&self, percent: f64) -> Option<()> {
+        let max = self.read_max_brightness_raw().await?;
+        let value = (percent.clamp(0.0, 100.0) / 100.0 * max).round() as u32;
+        let connection = self
+            .connection
+            .get_or_try_init(Connection::system)
+            .await
+            .map_err(|e| warn!("Cannot connect to the system bus: {}", e))
+            .ok()?;
+        connection
+            .call_method(
+                Some("org.freedesktop.login1"),
+                "/org/freedesktop/login1/session/auto",
+                Some("org.freedesktop.login1.Session"),
+                "SetBrightness",
+                &("backlight", self.device.as_str(), value),
+            )
+            .await
+            .map_err(|e| debug!("SetBrightness failed: {}", e))
+            .ok()?;
+        Some(())
+    }
+
+    /// Watches the brightness file, the changes made by other programs are shown immediately
+    fn subscribe(&mut self, sender: HookSender) -> bool {
+        let mut inotify = match Inotify::init() {
+            Ok(inotify) => inotify,
+            Err(e) => {
+                warn!("Cannot watch {:?}: {}", self.brightness_path, e);
+                return false;
+            }
+        };
+        if let Err(e) = inotify
+            .watches()
+            .add(&self.brightness_path, WatchMask::MODIFY)
+        {
+            warn!("Cannot watch {:?}: {}", self.brightness_path, e);
+            return false;
+        }
+        thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            // the watch outlives the provider, it ends at the first write after a reload
+            while inotify.read_events_blocking(&mut buffer).is_ok() {
+                if sender.send_blocking().is_err() {
+                    break;
+                }
+            }
+        });
+        true
+    }
 }
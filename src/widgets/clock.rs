@@ -1,18 +1,29 @@
-use crate::utils::{HookSender, TimedHooks};
+use crate::utils::{set_source_rgba, style::rounded_rectangle, HookSender, Key, TimedHooks};
 use crate::{
+    statusbar::{Popup, PopupAction},
+    utils::{Color, MouseButton},
     widget_default,
-    widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
+    widgets::{
+        popup::{self, draw_background, highlight, markup_layout},
+        Rectangle, Result, Text, Widget, WidgetConfig,
+    },
 };
 use async_trait::async_trait;
 use cairo::Context;
-use chrono::Local;
+use chrono::{Datelike, Days, Local, Months, NaiveDate};
 use log::debug;
-use std::fmt::{Debug, Display};
+use pangocairo::show_layout;
+use std::{
+    fmt::{Debug, Display},
+    time::Duration,
+};
 
-/// Displays a datetime
+/// Displays a datetime, a left click opens a calendar
 pub struct Clock {
     format: String,
     inner: Text,
+    update_interval: Duration,
+    popup_config: WidgetConfig,
 }
 
 impl Debug for Clock {
@@ -33,6 +44,8 @@ impl Clock {
         let format = format.to_string();
         Box::new(Self {
             inner: *Text::new("", config).await,
+            update_interval: config.update_interval,
+            popup_config: config.clone(),
             format,
         })
     }
@@ -40,19 +53,30 @@ impl Clock {
 
 #[async_trait]
 impl Widget for Clock {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating clock");
         let text = Local::now().format(&self.format);
-        self.inner.set_text(text);
-        Ok(())
+        Ok(self.inner.set_text(text))
     }
 
     async fn hook(&mut self, sender: HookSender, timed_hooks: &mut TimedHooks) -> Result<()> {
-        timed_hooks.subscribe(sender);
+        timed_hooks.subscribe(self.update_interval, sender);
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    async fn popup(&mut self, button: MouseButton) -> Result<Option<Box<dyn Popup>>> {
+        Ok(match button {
+            MouseButton::Left => Some(Box::new(Calendar::new(&self.popup_config))),
+            _ => None,
+        })
+    }
+
+    /// The full date
+    fn tooltip(&self) -> Option<String> {
+        Some(Local::now().format("%A %-d %B %Y, %H:%M:%S").to_string())
+    }
+
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for Clock {
@@ -61,6 +85,159 @@ impl Display for Clock {
     }
 }
 
+/// A month of days, starting on monday
+#[derive(Debug)]
+struct Calendar {
+    /// first day of the month shown
+    month: NaiveDate,
+    today: NaiveDate,
+    config: WidgetConfig,
+}
+
+impl Calendar {
+    /// The header, the names of the days and up to 6 weeks
+    const ROWS: u32 = 8;
+
+    fn new(config: &WidgetConfig) -> Self {
+        let today = Local::now().date_naive();
+        Self {
+            month: today.with_day(1).unwrap_or(today),
+            today,
+            config: config.clone(),
+        }
+    }
+
+    fn cell_size(&self) -> (u32, u32) {
+        (
+            (self.config.font_size * 2.4).ceil() as u32,
+            (self.config.font_size * 1.6).ceil() as u32,
+        )
+    }
+
+    fn padding(&self) -> u32 {
+        (self.config.font_size / 2.0) as u32
+    }
+
+    fn previous_month(&mut self) {
+        self.month = self
+            .month
+            .checked_sub_months(Months::new(1))
+            .unwrap_or(self.month);
+    }
+
+    fn next_month(&mut self) {
+        self.month = self
+            .month
+            .checked_add_months(Months::new(1))
+            .unwrap_or(self.month);
+    }
+}
+
+impl Popup for Calendar {
+    fn size(&self, _context: &Context) -> Result<(u32, u32)> {
+        let (cell_width, cell_height) = self.cell_size();
+        let padding = self.padding();
+        Ok((
+            7 * cell_width + 2 * padding,
+            Self::ROWS * cell_height + 2 * padding,
+        ))
+    }
+
+    fn draw(&self, context: &Context, width: u32, height: u32) -> Result<()> {
+        draw_background(context, &self.config, width, height)?;
+        let layout = markup_layout(context, &self.config)?;
+        let (cell_width, cell_height) = self.cell_size();
+        let (cell_width, cell_height) = (f64::from(cell_width), f64::from(cell_height));
+        let padding = f64::from(self.padding());
+        let dimmed = Color {
+            a: self.config.fg_color.a * 0.5,
+            ..self.config.fg_color
+        };
+        // centers `text` in the cell at `column` and `row`, spanning `columns` cells
+        let show = |text: &str, column: u32, row: u32, columns: u32, color: Color| {
+            layout.set_text(text);
+            let (text_width, text_height) = layout.pixel_size();
+            set_source_rgba(context, color);
+            context.move_to(
+                padding
+                    + f64::from(column) * cell_width
+                    + (f64::from(columns) * cell_width - f64::from(text_width)) / 2.0,
+                padding
+                    + f64::from(row) * cell_height
+                    + (cell_height - f64::from(text_height)) / 2.0,
+            );
+            show_layout(context, &layout);
+        };
+
+        show("‹", 0, 0, 1, self.config.fg_color);
+        show(
+            &self.month.format("%B %Y").to_string(),
+            1,
+            0,
+            5,
+            self.config.fg_color,
+        );
+        show("›", 6, 0, 1, self.config.fg_color);
+
+        let offset = self.month.weekday().num_days_from_monday();
+        let start = self.month - Days::new(u64::from(offset));
+        for (column, day) in start.iter_days().take(7).enumerate() {
+            let name: String = day.format("%a").to_string().chars().take(2).collect();
+            show(&name, column as u32, 1, 1, dimmed);
+        }
+        for (cell, day) in start.iter_days().take(42).enumerate() {
+            let (column, row) = (cell as u32 % 7, cell as u32 / 7 + 2);
+            if day == self.today {
+                set_source_rgba(context, highlight(self.config.fg_color));
+                rounded_rectangle(
+                    context,
+                    padding + f64::from(column) * cell_width,
+                    padding + f64::from(row) * cell_height,
+                    cell_width,
+                    cell_height,
+                    padding / 2.0,
+                );
+                context.fill().map_err(popup::Error::from)?;
+            }
+            let color = if day.month() == self.month.month() {
+                self.config.fg_color
+            } else {
+                dimmed
+            };
+            show(&day.day().to_string(), column, row, 1, color);
+        }
+        Ok(())
+    }
+
+    fn on_click(&mut self, button: MouseButton, x: u32, y: u32) -> Result<PopupAction> {
+        let (cell_width, cell_height) = self.cell_size();
+        let padding = self.padding();
+        match button {
+            MouseButton::Left if y >= padding && y < padding + cell_height => {
+                if x < padding + cell_width {
+                    self.previous_month();
+                } else if x >= padding + 6 * cell_width {
+                    self.next_month();
+                }
+            }
+            MouseButton::ScrollUp => self.previous_month(),
+            MouseButton::ScrollDown => self.next_month(),
+            _ => {}
+        }
+        Ok(PopupAction::Keep)
+    }
+
+    fn on_key(&mut self, key: Key) -> Result<PopupAction> {
+        match key {
+            Key::Left | Key::PageUp => self.previous_month(),
+            Key::Right | Key::PageDown => self.next_month(),
+            Key::Home => self.month = self.today.with_day(1).unwrap_or(self.today),
+            _ => {}
+        }
+        Ok(PopupAction::Keep)
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error(transparent)]
 pub enum Error {}
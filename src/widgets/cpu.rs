@@ -1,5 +1,5 @@
 use crate::{
-    utils::{HookSender, TimedHooks},
+    utils::{HookSender, Template, TimedHooks},
     widget_default,
     widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
 };
@@ -7,58 +7,70 @@ use async_trait::async_trait;
 use cairo::Context;
 use log::debug;
 use psutil::cpu::{CpuPercentCollector, CpuTimesPercentCollector};
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
 /// Displays cpu informations
 #[derive(Debug)]
 pub struct Cpu {
-    format: String,
+    format: Template,
     per: CpuPercentCollector,
     times: CpuTimesPercentCollector,
     inner: Text,
+    update_interval: Duration,
 }
 
 impl Cpu {
-    ///* `format`
-    ///  * *%p* will be replaced with the cpu usage percentage
-    ///  * *%u* will be replaced with the time spent in user mode
-    ///  * *%s* will be replaced with the time spent in system mode
-    ///  * *%i* will be replaced with the time spent idle
-    ///  * *%b* will be replaced with the time spent busy
+    /// Format used when the config file doesn't set one
+    pub const DEFAULT_FORMAT: &'static str = "{percent: >4.1}%";
+    /// Legacy `%x` tokens of the format
+    pub(crate) const ALIASES: &'static [(&'static str, &'static str)] = &[
+        ("%p", "{percent: >4.1}"),
+        ("%u", "{user: >4.1}"),
+        ("%s", "{system: >4.1}"),
+        ("%i", "{idle: >4.1}"),
+        ("%b", "{busy: >4.1}"),
+    ];
+
+    ///* `format` a [Template](crate::utils::format)
+    ///  * *{percent}* or *%p* the cpu usage percentage
+    ///  * *{user}* or *%u* the time spent in user mode
+    ///  * *{system}* or *%s* the time spent in system mode
+    ///  * *{idle}* or *%i* the time spent idle
+    ///  * *{busy}* or *%b* the time spent busy
     ///* `config` a [&WidgetConfig]
     pub async fn new(format: impl ToString, config: &WidgetConfig) -> Result<Box<Self>> {
         Ok(Box::new(Self {
-            format: format.to_string(),
+            format: Template::with_aliases(&format.to_string(), Self::ALIASES),
             per: CpuPercentCollector::new().map_err(Error::from)?,
             times: CpuTimesPercentCollector::new().map_err(Error::from)?,
             inner: *Text::new("", config).await,
+            update_interval: config.update_interval,
         }))
     }
 }
 
 #[async_trait]
 impl Widget for Cpu {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating cpu");
         let times = self.times.cpu_times_percent().map_err(Error::from)?;
         let cpu_percent = self.per.cpu_percent().map_err(Error::from)?;
-        let text = self
-            .format
-            .replace("%p", &format!("{: >4.1}", cpu_percent))
-            .replace("%u", &format!("{: >4.1}", times.user()))
-            .replace("%s", &format!("{: >4.1}", times.system()))
-            .replace("%i", &format!("{: >4.1}", times.idle()))
-            .replace("%b", &format!("{: >4.1}", times.busy()));
-        self.inner.set_text(text);
-        Ok(())
+        let values = [
+            ("percent", cpu_percent.into()),
+            ("user", times.user().into()),
+            ("system", times.system().into()),
+            ("idle", times.idle().into()),
+            ("busy", times.busy().into()),
+        ];
+        Ok(self.inner.set_level(cpu_percent.into()) | self.inner.render(&self.format, &values))
     }
 
     async fn hook(&mut self, sender: HookSender, timed_hooks: &mut TimedHooks) -> Result<()> {
-        timed_hooks.subscribe(sender);
+        timed_hooks.subscribe(self.update_interval, sender);
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for Cpu {
@@ -1,25 +1,39 @@
-use crate::utils::{bytes_to_closest, HookSender, TimedHooks};
+use crate::utils::{format::Value, HookSender, Template, TimedHooks};
 use crate::{
     widget_default,
     widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
 };
 use async_trait::async_trait;
 use cairo::Context;
-use std::fmt::Display;
+use pango::glib::markup_escape_text;
+use psutil::disk::DiskUsage;
+use std::{fmt::Display, time::Duration};
 
 #[derive(Debug)]
 pub struct Disk {
-    format: String,
+    format: Template,
     path: String,
     inner: Text,
+    update_interval: Duration,
+    usage: Option<DiskUsage>,
 }
 
 impl Disk {
-    ///* `format`
-    ///  * *%p* will be replaced with the disk used percent
-    ///  * *%u* will be replaced with the used disk
-    ///  * *%f* will be replaced with the free disk
-    ///  * *%t* will be replaced with the total disk
+    /// Format used when the config file doesn't set one
+    pub const DEFAULT_FORMAT: &'static str = "{free}";
+    /// Legacy `%x` tokens of the format
+    pub(crate) const ALIASES: &'static [(&'static str, &'static str)] = &[
+        ("%p", "{percent}"),
+        ("%u", "{used}"),
+        ("%f", "{free}"),
+        ("%t", "{total}"),
+    ];
+
+    ///* `format` a [Template](crate::utils::format)
+    ///  * *{percent}* or *%p* the disk used percent
+    ///  * *{used}* or *%u* the used disk
+    ///  * *{free}* or *%f* the free disk
+    ///  * *{total}* or *%t* the total disk
     ///* `config` a [&WidgetConfig]
     pub async fn new(
         format: impl ToString,
@@ -27,33 +41,53 @@ impl Disk {
         config: &WidgetConfig,
     ) -> Box<Self> {
         Box::new(Self {
-            format: format.to_string(),
+            format: Template::with_aliases(&format.to_string(), Self::ALIASES),
             path: path.to_string(),
             inner: *Text::new("", config).await,
+            update_interval: config.update_interval,
+            usage: None,
         })
     }
 }
 
 #[async_trait]
 impl Widget for Disk {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         let disk_usage = psutil::disk::disk_usage(self.path.clone()).map_err(Error::from)?;
-        let text = self
-            .format
-            .replace("%p", &disk_usage.percent().to_string())
-            .replace("%u", &bytes_to_closest(disk_usage.used()))
-            .replace("%f", &bytes_to_closest(disk_usage.free()))
-            .replace("%t", &bytes_to_closest(disk_usage.total()));
-        self.inner.set_text(text);
-        Ok(())
+        let values = [
+            ("percent", disk_usage.percent().into()),
+            ("used", Value::Bytes(disk_usage.used())),
+            ("free", Value::Bytes(disk_usage.free())),
+            ("total", Value::Bytes(disk_usage.total())),
+        ];
+        let changed = self.inner.set_level(disk_usage.percent().into())
+            | self.inner.render(&self.format, &values);
+        self.usage = Some(disk_usage);
+        Ok(changed)
     }
 
     async fn hook(&mut self, sender: HookSender, timed_hooks: &mut TimedHooks) -> Result<()> {
-        timed_hooks.subscribe(sender);
+        timed_hooks.subscribe(self.update_interval, sender);
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    /// All the values of the disk
+    fn tooltip(&self) -> Option<String> {
+        let usage = self.usage.as_ref()?;
+        let values = [
+            ("path", self.path.as_str().into()),
+            ("percent", usage.percent().into()),
+            ("used", Value::Bytes(usage.used())),
+            ("free", Value::Bytes(usage.free())),
+            ("total", Value::Bytes(usage.total())),
+        ];
+        let template = Template::parse(
+            "<b>{path}</b>\nUsed: {used:.1} ({percent:.1}%)\nFree: {free:.1}\nTotal: {total:.1}",
+        );
+        Some(template.render_escaped(&values, |text| markup_escape_text(text).to_string()))
+    }
+
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for Disk {
@@ -1,5 +1,5 @@
 use crate::{
-    utils::bytes_to_closest,
+    utils::{format::Value, HookSender, Template, TimedHooks},
     widget_default,
     widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
 };
@@ -7,48 +7,65 @@ use async_trait::async_trait;
 use cairo::Context;
 use log::debug;
 use psutil::memory::virtual_memory;
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
 /// Displays memory informations
 #[derive(Debug)]
 pub struct Memory {
-    format: String,
+    format: Template,
     inner: Text,
+    update_interval: Duration,
 }
 
 impl Memory {
-    ///* `format`
-    ///  * *%p* will be replaced with the usage percentage
-    ///  * *%t* will be replaced with the total ram
-    ///  * *%a* will be replaced with the available ram
-    ///  * *%u* will be replaced with the used ram
-    ///  * *%f* will be replaced with the free ram
+    /// Format used when the config file doesn't set one
+    pub const DEFAULT_FORMAT: &'static str = "{percent:.2}%";
+    /// Legacy `%x` tokens of the format
+    pub(crate) const ALIASES: &'static [(&'static str, &'static str)] = &[
+        ("%p", "{percent:.2}"),
+        ("%t", "{total}"),
+        ("%a", "{available}"),
+        ("%u", "{used}"),
+        ("%f", "{free}"),
+    ];
+
+    ///* `format` a [Template](crate::utils::format)
+    ///  * *{percent}* or *%p* the usage percentage
+    ///  * *{total}* or *%t* the total ram
+    ///  * *{available}* or *%a* the available ram
+    ///  * *{used}* or *%u* the used ram
+    ///  * *{free}* or *%f* the free ram
     ///* `config` a [&WidgetConfig]
     pub async fn new(format: impl ToString, config: &WidgetConfig) -> Box<Self> {
         Box::new(Self {
-            format: format.to_string(),
+            format: Template::with_aliases(&format.to_string(), Self::ALIASES),
             inner: *Text::new("", config).await,
+            update_interval: config.update_interval,
         })
     }
 }
 
 #[async_trait]
 impl Widget for Memory {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating memory");
         let ram = virtual_memory().map_err(Error::from)?;
-        let text = self
-            .format
-            .replace("%p", &format!("{:.2}", ram.percent()))
-            .replace("%t", &bytes_to_closest(ram.total()))
-            .replace("%a", &bytes_to_closest(ram.available()))
-            .replace("%u", &bytes_to_closest(ram.used()))
-            .replace("%f", &bytes_to_closest(ram.free()));
-        self.inner.set_text(text);
+        let values = [
+            ("percent", ram.percent().into()),
+            ("total", Value::Bytes(ram.total())),
+            ("available", Value::Bytes(ram.available())),
+            ("used", Value::Bytes(ram.used())),
+            ("free", Value::Bytes(ram.free())),
+        ];
+        Ok(self.inner.set_level(ram.percent().into()) | self.inner.render(&self.format, &values))
+    }
+
+    async fn hook(&mut self, sender: HookSender, timed_hooks: &mut TimedHooks) -> Result<()> {
+        timed_hooks.subscribe(self.update_interval, sender);
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for Memory {
@@ -0,0 +1,69 @@
+use crate::{
+    statusbar::Popup,
+    utils::{HookSender, MouseButton, StatusBarInfo, TimedHooks},
+    widget_default,
+    widgets::{Rectangle, Result, Volume, VolumeIcons, VolumeProvider, Widget, WidgetConfig},
+};
+use async_trait::async_trait;
+use cairo::Context;
+use log::debug;
+use std::fmt::Display;
+
+/// Displays status and volume of the input device
+#[derive(Debug)]
+pub struct Microphone {
+    inner: Volume,
+}
+
+impl Microphone {
+    ///* `format` a [Template](crate::utils::format), see [Volume::new] for the placeholders
+    ///* `provider` a [VolumeProvider] of the input device,
+    ///  e.g. [PulseaudioProvider::source](crate::widgets::PulseaudioProvider::source)
+    ///* `icons` sets a custom [VolumeIcons], [VolumeIcons::microphone] by default
+    ///* `config` a [&WidgetConfig]
+    pub async fn new(
+        format: impl ToString,
+        provider: Box<impl VolumeProvider + 'static>,
+        icons: Option<VolumeIcons>,
+        config: &WidgetConfig,
+    ) -> Box<Self> {
+        let icons = icons.unwrap_or_else(VolumeIcons::microphone);
+        Box::new(Self {
+            inner: *Volume::new(format, provider, Some(icons), config).await,
+        })
+    }
+}
+
+#[async_trait]
+impl Widget for Microphone {
+    async fn update(&mut self) -> Result<bool> {
+        debug!("updating microphone");
+        self.inner.update().await
+    }
+
+    fn setup(&mut self, info: &StatusBarInfo) -> Result<()> {
+        self.inner.setup(info)
+    }
+
+    async fn hook(&mut self, sender: HookSender, timed_hooks: &mut TimedHooks) -> Result<()> {
+        self.inner.hook(sender, timed_hooks).await
+    }
+
+    /// A left click mutes or unmutes the microphone, scrolling changes its volume
+    async fn on_click(&mut self, button: MouseButton, x: u32, y: u32) -> Result<()> {
+        self.inner.on_click(button, x, y).await
+    }
+
+    /// A right click opens a list of the input devices
+    async fn popup(&mut self, button: MouseButton) -> Result<Option<Box<dyn Popup>>> {
+        self.inner.popup(button).await
+    }
+
+    widget_default!(draw, size, padding, style);
+}
+
+impl Display for Microphone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        String::from("Microphone").fmt(f)
+    }
+}
@@ -1,12 +1,17 @@
-use crate::utils::{Color, HookSender, Rectangle, StatusBarInfo, TimedHooks};
+use crate::{
+    statusbar::Popup,
+    utils::{
+        Color, HookSender, MouseButton, Rectangle, StatusBarInfo, Style, Thresholds, TimedHooks,
+    },
+};
 use async_trait::async_trait;
 use cairo::Context;
-use std::{fmt::Display, time::Duration};
+use std::{any::Any, fmt::Display, time::Duration};
 use thiserror::Error;
 
 mod replaceable;
 
-pub use replaceable::ReplaceableWidget;
+pub use replaceable::{ReplaceableWidget, SupervisionPolicy};
 
 mod active_window;
 mod bat;
@@ -15,7 +20,9 @@ mod clock;
 mod cpu;
 mod disk;
 mod memory;
+mod microphone;
 mod network;
+mod popup;
 mod spacer;
 mod systray;
 mod temp;
@@ -27,20 +34,27 @@ mod wlan;
 mod workspaces;
 
 pub use active_window::ActiveWindow;
-pub use bat::{Battery, BatteryIcons, LowBatteryWarner, NotifySend};
-pub use brightness::{Brightness, BrightnessProvider, LightProvider, SysfsProvider};
+pub use bat::{
+    Battery, BatteryAction, BatteryIcons, BatterySelection, BatteryStatus, ChargeState,
+    LowBatteryWarner, NotifySend, PowerSupply, Threshold, ThresholdWarner,
+};
+pub use brightness::{
+    Brightness, BrightnessProvider, BrightnessctlProvider, LightProvider, SysfsProvider,
+};
 pub use clock::Clock;
 pub use cpu::Cpu;
 pub use disk::Disk;
 pub use memory::Memory;
+pub use microphone::Microphone;
 pub use network::{Network, NetworkIcons};
+pub use popup::ListPopup;
 pub use spacer::Spacer;
 pub use systray::Systray;
 pub use temp::Temperatures;
 pub use text::Text;
 pub use update::{Apt, Update, UpdateSource};
-pub use volume::{PulseaudioProvider, Volume, VolumeIcons, VolumeProvider};
-pub use weather::{MeteoIcons, Weather, WeatherProvider, OpenMeteoProvider};
+pub use volume::{AudioDevice, PulseaudioProvider, Volume, VolumeIcons, VolumeProvider};
+pub use weather::{MeteoIcons, OpenMeteoProvider, Weather, WeatherProvider};
 pub use wlan::Wlan;
 pub use workspaces::{
     ActiveProvider, NeverHide, WorkspaceHider, WorkspaceStatus, WorkspaceStatusProvider, Workspaces,
@@ -67,19 +81,46 @@ impl Size {
 pub type Result<T> = std::result::Result<T, WidgetError>;
 
 #[async_trait]
-pub trait Widget: std::fmt::Debug + Display + Send {
+pub trait Widget: Any + std::fmt::Debug + Display + Send {
     fn draw(&self, context: &Context, rectangle: &Rectangle) -> Result<()>;
     fn setup(&mut self, _info: &StatusBarInfo) -> Result<()> {
         Ok(())
     }
-    async fn update(&mut self) -> Result<()> {
-        Ok(())
+    /// Refreshes the content of the widget, returns true if it changed and needs to be redrawn
+    async fn update(&mut self) -> Result<bool> {
+        Ok(false)
     }
     async fn hook(&mut self, _sender: HookSender, _pool: &mut TimedHooks) -> Result<()> {
         Ok(())
     }
+    /// Called when a button is pressed over the widget, `x` and `y` are relative to its region
+    async fn on_click(&mut self, _button: MouseButton, _x: u32, _y: u32) -> Result<()> {
+        Ok(())
+    }
+    /// Called when a button is released over the widget, `x` and `y` are relative to its region
+    async fn on_release(&mut self, _button: MouseButton, _x: u32, _y: u32) -> Result<()> {
+        Ok(())
+    }
     fn size(&self, context: &Context) -> Result<Size>;
     fn padding(&self) -> u32;
+    /// The [Text] displayed by the widget, if its content can be set from outside
+    fn as_text_mut(&mut self) -> Option<&mut Text> {
+        None
+    }
+    /// Decorations painted by the bar around the widget
+    fn style(&self) -> Style {
+        Style::default()
+    }
+    /// The [Popup] opened by a click with `button`, `None` if the click is handled by
+    /// [Widget::on_click] instead
+    async fn popup(&mut self, _button: MouseButton) -> Result<Option<Box<dyn Popup>>> {
+        Ok(None)
+    }
+    /// Text shown in a window when the pointer stays over the widget,
+    /// parsed as [Pango markup](https://docs.gtk.org/Pango/pango_markup.html)
+    fn tooltip(&self) -> Option<String> {
+        None
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -90,6 +131,17 @@ pub struct WidgetConfig {
     pub fg_color: Color,
     pub hide_timeout: Duration,
     pub flex: bool,
+    /// how often timed widgets are updated
+    pub update_interval: Duration,
+    /// parse the text as [Pango markup](https://docs.gtk.org/Pango/pango_markup.html)
+    pub markup: bool,
+    /// foreground color depending on the value shown by the widget, replaces `fg_color`
+    pub fg_thresholds: Thresholds,
+    /// background color of the region depending on the value shown by the widget
+    pub bg_thresholds: Thresholds,
+    pub style: Style,
+    /// widgets showing changes, like [Volume], also show them in an [Osd](crate::backend::Osd)
+    pub osd: bool,
 }
 
 impl WidgetConfig {
@@ -108,6 +160,7 @@ impl WidgetConfig {
             fg_color,
             hide_timeout,
             flex,
+            ..Self::default()
         }
     }
 }
@@ -121,6 +174,12 @@ impl Default for WidgetConfig {
             fg_color: Color::new(1.0, 1.0, 1.0, 1.0),
             hide_timeout: Duration::from_secs(1),
             flex: false,
+            update_interval: Duration::from_secs(1),
+            markup: false,
+            fg_thresholds: Thresholds::default(),
+            bg_thresholds: Thresholds::default(),
+            style: Style::default(),
+            osd: false,
         }
     }
 }
@@ -136,6 +195,7 @@ pub enum WidgetError {
     Disk(#[from] disk::Error),
     Memory(#[from] memory::Error),
     Network(#[from] network::Error),
+    Popup(#[from] popup::Error),
     #[error("Spacer")]
     Spacer,
     Systray(#[from] systray::Error),
@@ -166,6 +226,11 @@ macro_rules! widget_default {
             self.inner.draw(context, rectangle)
         }
     };
+    (style) => {
+        fn style(&self) -> $crate::utils::Style {
+            self.inner.style()
+        }
+    };
     ($a:ident, $($b:tt)*) => {
         widget_default!($a);
         widget_default!($($b)*);
@@ -1,4 +1,5 @@
 use crate::{
+    utils::{HookSender, Template, TimedHooks},
     widget_default,
     widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
 };
@@ -8,6 +9,7 @@ use log::debug;
 use std::{
     fmt::Display,
     fs::{metadata, read_to_string},
+    time::Duration,
 };
 
 fn get_interface_stats(ifname: &str) -> Result<(bool, bool)> {
@@ -45,17 +47,25 @@ impl Default for NetworkIcons {
 /// Displays informations about a network interface
 #[derive(Debug)]
 pub struct Network {
-    format: String,
+    format: Template,
     interface: String,
     icons: NetworkIcons,
     inner: Text,
+    update_interval: Duration,
 }
 
 impl Network {
-    ///* `format`
-    ///  * `%n` will be replaced with the interface name
-    ///  * `%s` will be replaced with the interface status
-    ///  * `%t` will be replaced with the interface type
+    /// Format used when the config file doesn't set one
+    pub const DEFAULT_FORMAT: &'static str = "{interface} {status}";
+    /// Legacy `%x` tokens of the format
+    pub(crate) const ALIASES: &'static [(&'static str, &'static str)] =
+        &[("%n", "{interface}"), ("%s", "{status}"), ("%t", "{type}")];
+
+    ///* `format` a [Template](crate::utils::format)
+    ///  * `{interface}` or `%n` the interface name
+    ///  * `{status}` or `%s` the icon of the interface status
+    ///  * `{type}` or `%t` the icon of the interface type
+    ///  * `{online}` and `{wireless}` true if the interface is up or wireless
     ///* `icons` sets a custom [NetworkIcons]
     ///* `interface` name of the network interface
     ///* `fg_color` foreground color
@@ -66,9 +76,10 @@ impl Network {
         config: &WidgetConfig,
     ) -> Box<Self> {
         Box::new(Self {
-            format: format.to_string(),
+            format: Template::with_aliases(&format.to_string(), Self::ALIASES),
             interface,
             inner: *Text::new("", config).await,
+            update_interval: config.update_interval,
             icons: icons.unwrap_or_default(),
         })
     }
@@ -76,33 +87,37 @@ impl Network {
 
 #[async_trait]
 impl Widget for Network {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating network");
-        let text = if let Ok((wireless, online)) = get_interface_stats(&self.interface) {
-            self.format
-                .replace("%n", &self.interface)
-                .replace("%s", {
-                    if online {
-                        self.icons.online.as_str()
-                    } else {
-                        self.icons.offline.as_str()
-                    }
-                })
-                .replace("%t", {
-                    if wireless {
-                        self.icons.wireless.as_str()
-                    } else {
-                        self.icons.ethernet.as_str()
-                    }
-                })
+        let Ok((wireless, online)) = get_interface_stats(&self.interface) else {
+            return Ok(self.inner.set_text("No interface"));
+        };
+        let status = if online {
+            &self.icons.online
+        } else {
+            &self.icons.offline
+        };
+        let kind = if wireless {
+            &self.icons.wireless
         } else {
-            "No interface".to_string()
+            &self.icons.ethernet
         };
-        self.inner.set_text(text);
+        let values = [
+            ("interface", self.interface.as_str().into()),
+            ("status", status.as_str().into()),
+            ("type", kind.as_str().into()),
+            ("online", online.into()),
+            ("wireless", wireless.into()),
+        ];
+        Ok(self.inner.render(&self.format, &values))
+    }
+
+    async fn hook(&mut self, sender: HookSender, timed_hooks: &mut TimedHooks) -> Result<()> {
+        timed_hooks.subscribe(self.update_interval, sender);
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for Network {
@@ -0,0 +1,297 @@
+use crate::{
+    statusbar::{Popup, PopupAction},
+    utils::{set_source_rgba, style::rounded_rectangle, Color, Key, MouseButton, Rectangle, Side},
+    widgets::{Result, WidgetConfig},
+};
+use cairo::Context;
+use log::debug;
+use pango::{parse_markup, FontDescription, Layout};
+use pangocairo::{create_layout, show_layout};
+use std::{fmt::Debug, ops::Range};
+
+/// Rows shown at once by a [ListPopup], the others are reached by scrolling
+const MAX_ROWS: usize = 20;
+
+/// A [Popup] listing lines of [Pango markup](https://docs.gtk.org/Pango/pango_markup.html)
+///
+/// With [ListPopup::on_select] a line is chosen by clicking it,
+/// or by moving the selection with the arrows and pressing Enter.
+pub struct ListPopup {
+    items: Vec<String>,
+    config: WidgetConfig,
+    selected: usize,
+    /// index of the first visible item
+    first: usize,
+    on_select: Option<Box<dyn FnMut(usize) + Send>>,
+}
+
+impl Debug for ListPopup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ListPopup(items: {:?})", self.items)
+    }
+}
+
+impl ListPopup {
+    ///* `items` the lines of the list
+    ///* `config` the font, the colors and the [Style](crate::utils::Style) of the popup
+    pub fn new(items: Vec<String>, config: &WidgetConfig) -> Self {
+        Self {
+            items,
+            config: config.clone(),
+            selected: 0,
+            first: 0,
+            on_select: None,
+        }
+    }
+
+    /// Calls `f` with the index of the chosen item, the popup is closed afterwards
+    pub fn on_select(mut self, f: impl FnMut(usize) + Send + 'static) -> Self {
+        self.on_select = Some(Box::new(f));
+        self
+    }
+
+    /// Selects the item at `index` when the popup opens, e.g. the current value
+    pub fn selected(mut self, index: usize) -> Self {
+        self.select(index);
+        self
+    }
+
+    fn row_height(&self) -> u32 {
+        (self.config.font_size * 1.6).ceil() as u32
+    }
+
+    fn padding(&self) -> u32 {
+        (self.config.font_size / 2.0) as u32
+    }
+
+    fn visible(&self) -> Range<usize> {
+        self.first..self.items.len().min(self.first + MAX_ROWS)
+    }
+
+    fn scroll(&mut self, delta: isize) {
+        let last_first = self.items.len().saturating_sub(MAX_ROWS);
+        self.first = self.first.saturating_add_signed(delta).min(last_first);
+    }
+
+    /// Moves the selection to `index` and scrolls to keep it visible
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.items.len().saturating_sub(1));
+        if self.selected < self.first {
+            self.first = self.selected;
+        } else if self.selected >= self.first + MAX_ROWS {
+            self.first = self.selected + 1 - MAX_ROWS;
+        }
+    }
+
+    fn choose(&mut self, index: usize) -> PopupAction {
+        match &mut self.on_select {
+            Some(on_select) if index < self.items.len() => {
+                on_select(index);
+                PopupAction::Close
+            }
+            _ => PopupAction::Keep,
+        }
+    }
+}
+
+impl Popup for ListPopup {
+    fn size(&self, context: &Context) -> Result<(u32, u32)> {
+        let layout = markup_layout(context, &self.config)?;
+        let width = self
+            .items
+            .iter()
+            .map(|item| {
+                set_markup(&layout, item);
+                layout.pixel_size().0 as u32
+            })
+            .max()
+            .unwrap_or(0);
+        let rows = self.visible().len().max(1) as u32;
+        Ok((
+            width + 2 * self.padding(),
+            rows * self.row_height() + 2 * self.padding(),
+        ))
+    }
+
+    fn draw(&self, context: &Context, width: u32, height: u32) -> Result<()> {
+        draw_background(context, &self.config, width, height)?;
+        let layout = markup_layout(context, &self.config)?;
+        let padding = f64::from(self.padding());
+        let row_height = f64::from(self.row_height());
+        for (row, index) in self.visible().enumerate() {
+            let y = padding + row as f64 * row_height;
+            if self.on_select.is_some() && index == self.selected {
+                set_source_rgba(context, highlight(self.config.fg_color));
+                rounded_rectangle(
+                    context,
+                    padding / 2.0,
+                    y,
+                    f64::from(width) - padding,
+                    row_height,
+                    padding / 2.0,
+                );
+                context.fill().map_err(Error::from)?;
+            }
+            set_markup(&layout, &self.items[index]);
+            set_source_rgba(context, self.config.fg_color);
+            let text_height = f64::from(layout.pixel_size().1);
+            context.move_to(padding, y + (row_height - text_height) / 2.0);
+            show_layout(context, &layout);
+        }
+        Ok(())
+    }
+
+    fn on_click(&mut self, button: MouseButton, _x: u32, y: u32) -> Result<PopupAction> {
+        match button {
+            MouseButton::Left => {
+                let Some(row) = y.checked_sub(self.padding()) else {
+                    return Ok(PopupAction::Keep);
+                };
+                let index = self.first + (row / self.row_height()) as usize;
+                // the bottom padding is below the last visible row
+                if !self.visible().contains(&index) {
+                    return Ok(PopupAction::Keep);
+                }
+                Ok(self.choose(index))
+            }
+            MouseButton::ScrollUp => {
+                self.scroll(-1);
+                Ok(PopupAction::Keep)
+            }
+            MouseButton::ScrollDown => {
+                self.scroll(1);
+                Ok(PopupAction::Keep)
+            }
+            _ => Ok(PopupAction::Keep),
+        }
+    }
+
+    fn on_key(&mut self, key: Key) -> Result<PopupAction> {
+        if self.on_select.is_none() {
+            // nothing to select, the keys scroll the list
+            match key {
+                Key::Up => self.scroll(-1),
+                Key::Down => self.scroll(1),
+                Key::PageUp => self.scroll(-(MAX_ROWS as isize)),
+                Key::PageDown => self.scroll(MAX_ROWS as isize),
+                _ => {}
+            }
+            return Ok(PopupAction::Keep);
+        }
+        match key {
+            Key::Up => self.select(self.selected.saturating_sub(1)),
+            Key::Down => self.select(self.selected + 1),
+            Key::PageUp => self.select(self.selected.saturating_sub(MAX_ROWS)),
+            Key::PageDown => self.select(self.selected + MAX_ROWS),
+            Key::Home => self.select(0),
+            Key::End => self.select(usize::MAX),
+            Key::Enter => return Ok(self.choose(self.selected)),
+            _ => {}
+        }
+        Ok(PopupAction::Keep)
+    }
+}
+
+/// Color of the selected item or of the current day
+pub(crate) fn highlight(fg_color: Color) -> Color {
+    Color {
+        a: fg_color.a * 0.25,
+        ..fg_color
+    }
+}
+
+/// Paints the background of a popup with the [Style](crate::utils::Style) of `config`,
+/// a dark one if it has no background
+pub(crate) fn draw_background(
+    context: &Context,
+    config: &WidgetConfig,
+    width: u32,
+    height: u32,
+) -> Result<()> {
+    let mut style = config.style.clone();
+    style.background = style.background.or(Some(Color::new(0.1, 0.1, 0.1, 0.95)));
+    style.margin = 0;
+    style.separator = None;
+    let region = Rectangle {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    };
+    style
+        .draw(context, &region, Side::Left)
+        .map_err(Error::from)?;
+    Ok(())
+}
+
+/// A layout with the font of `config`
+pub(crate) fn markup_layout(context: &Context, config: &WidgetConfig) -> Result<Layout> {
+    let layout = create_layout(context).ok_or(Error::Pango)?;
+    let mut font = FontDescription::from_string(&config.font);
+    font.set_absolute_size(config.font_size * f64::from(pango::SCALE));
+    layout.set_font_description(Some(&font));
+    Ok(layout)
+}
+
+pub(crate) fn set_markup(layout: &Layout, markup: &str) {
+    match parse_markup(markup, '\0') {
+        Ok((attributes, text, _)) => {
+            layout.set_text(&text);
+            layout.set_attributes(Some(&attributes));
+        }
+        Err(e) => {
+            // show the text as is rather than nothing
+            debug!("Invalid markup {:?}: {}", markup, e);
+            layout.set_text(markup);
+            layout.set_attributes(None);
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Cairo(#[from] cairo::Error),
+    #[error("Pango error")]
+    Pango,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn popup(items: usize) -> (ListPopup, Arc<Mutex<Option<usize>>>) {
+        let chosen = Arc::new(Mutex::new(None));
+        let chosen_clone = Arc::clone(&chosen);
+        let popup = ListPopup::new(
+            (0..items).map(|i| i.to_string()).collect(),
+            &WidgetConfig::default(),
+        )
+        .on_select(move |index| *chosen_clone.lock().unwrap() = Some(index));
+        (popup, chosen)
+    }
+
+    #[test]
+    fn click_selects_the_row() {
+        let (mut popup, chosen) = popup(30);
+        popup.scroll(5);
+        let y = popup.padding() + 2 * popup.row_height() + 1;
+        assert!(matches!(
+            popup.on_click(MouseButton::Left, 0, y).unwrap(),
+            PopupAction::Close
+        ));
+        assert_eq!(*chosen.lock().unwrap(), Some(7));
+    }
+
+    #[test]
+    fn click_in_the_bottom_padding_selects_nothing() {
+        let (mut popup, chosen) = popup(30);
+        let y = popup.padding() + MAX_ROWS as u32 * popup.row_height() + 1;
+        assert!(matches!(
+            popup.on_click(MouseButton::Left, 0, y).unwrap(),
+            PopupAction::Keep
+        ));
+        assert_eq!(*chosen.lock().unwrap(), None);
+    }
+}
@@ -1,85 +1,506 @@
 use crate::{
-    utils::{HookSender, Rectangle, StatusBarInfo, TimedHooks},
+    statusbar::Popup,
+    utils::{HookSender, MouseButton, Rectangle, StatusBarInfo, TimedHooks},
     widgets::{Size, Text, Widget, WidgetConfig, WidgetError},
 };
 use cairo::Context;
-use log::error;
+use log::{error, warn};
+use pango::glib::markup_escape_text;
 use std::{
+    any::Any,
     fmt,
     ops::{Deref, DerefMut},
+    time::{Duration, Instant},
 };
+use tokio::{spawn, time::sleep};
 
+/// How a [ReplaceableWidget] reacts when its widget fails
+#[derive(Debug, Clone)]
+pub struct SupervisionPolicy {
+    /// delay before retrying a failed update, doubled after every consecutive failure
+    pub initial_backoff: Duration,
+    /// upper bound of the delay between retries
+    pub max_backoff: Duration,
+    /// consecutive failures after which the widget is permanently replaced,
+    /// a failing draw counts once per update and a failing click not at all
+    pub max_failures: u32,
+    /// text displayed while the widget is failing, if `None` the last good content is kept
+    pub placeholder: Option<String>,
+}
+
+impl Default for SupervisionPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(300),
+            max_failures: 10,
+            placeholder: None,
+        }
+    }
+}
+
+impl SupervisionPolicy {
+    fn backoff(&self, failures: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(failures.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Wraps a widget and restarts it according to a [SupervisionPolicy] when it fails
 #[derive(Debug)]
-pub struct ReplaceableWidget(Box<dyn Widget>);
+pub struct ReplaceableWidget {
+    widget: Box<dyn Widget>,
+    name: Option<String>,
+    hidden: bool,
+    policy: Option<SupervisionPolicy>,
+    /// font, colors and style of the placeholders
+    config: Option<WidgetConfig>,
+    sender: Option<HookSender>,
+    failures: u32,
+    last_error: Option<String>,
+    next_retry: Option<Instant>,
+    /// a draw or size failure was counted since the last update,
+    /// the bar redraws a failing widget many times before it is updated again
+    frame_failed: bool,
+    placeholder: Option<Box<Text>>,
+    dirty: bool,
+}
 
 impl Deref for ReplaceableWidget {
     type Target = dyn Widget;
 
     fn deref(&self) -> &Self::Target {
-        self.0.as_ref()
+        self.widget.as_ref()
     }
 }
 
 impl DerefMut for ReplaceableWidget {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        self.0.as_mut()
+        self.widget.as_mut()
     }
 }
 
 impl fmt::Display for ReplaceableWidget {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        std::fmt::Display::fmt(&self.0, f)
+        std::fmt::Display::fmt(&self.widget, f)
     }
 }
 
 impl ReplaceableWidget {
     pub fn new(wd: Box<dyn Widget>) -> Self {
-        Self(wd)
+        Self {
+            widget: wd,
+            name: None,
+            hidden: false,
+            policy: None,
+            config: None,
+            sender: None,
+            failures: 0,
+            last_error: None,
+            next_retry: None,
+            frame_failed: false,
+            placeholder: None,
+            dirty: true,
+        }
+    }
+
+    /// Creates a widget that is referred to as `name` instead of its type
+    pub fn named(wd: Box<dyn Widget>, name: impl ToString) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            ..Self::new(wd)
+        }
+    }
+
+    /// Sets the [SupervisionPolicy] of this widget instead of the one of the bar
+    pub fn with_policy(mut self, policy: SupervisionPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Draws the placeholders shown while the widget fails with `config`
+    /// instead of the one of the bar, usually the config of the widget
+    pub fn with_config(mut self, config: WidgetConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Uses `policy` and `config` unless the widget already has its own
+    pub(crate) fn default_policy(&mut self, policy: &SupervisionPolicy, config: &WidgetConfig) {
+        self.policy.get_or_insert_with(|| policy.clone());
+        self.config.get_or_insert_with(|| config.clone());
+    }
+
+    /// The name given to the widget, or its type if it wasn't named
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.widget.to_string())
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// A hidden widget keeps updating but takes no space in the bar
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.dirty |= self.hidden != hidden;
+        self.hidden = hidden;
+    }
+
+    /// Forces the widget to be redrawn on the next frame
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Returns true if the widget changed since the last frame
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Returns true if the widget changed since the last call
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// The error of the last failure, `None` once the widget recovers
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// The tooltip of the widget, or the error that made it fail
+    pub fn tooltip(&self) -> Option<String> {
+        match &self.last_error {
+            Some(e) => Some(format!(
+                "<b>{}</b>\n{}",
+                markup_escape_text(&self.name()),
+                markup_escape_text(e)
+            )),
+            None => self.widget.tooltip(),
+        }
+    }
+
+    /// Swaps the wrapped widget, returning the previous one
+    pub fn replace_widget(&mut self, wd: Box<dyn Widget>) -> Box<dyn Widget> {
+        self.dirty = true;
+        std::mem::replace(&mut self.widget, wd)
+    }
+
+    /// Returns true if the wrapped widget is a `W`
+    pub fn is<W: Widget>(&self) -> bool {
+        (self.widget.as_ref() as &dyn Any).is::<W>()
+    }
+
+    pub fn into_inner(self) -> Box<dyn Widget> {
+        self.widget
+    }
+
+    fn policy(&self) -> SupervisionPolicy {
+        self.policy.clone().unwrap_or_default()
+    }
+
+    fn config(&self) -> WidgetConfig {
+        self.config.clone().unwrap_or_default()
+    }
+
+    /// The placeholder is displayed only while the widget is failing
+    fn displayed(&self) -> &dyn Widget {
+        match &self.placeholder {
+            Some(placeholder) if self.failures > 0 => placeholder.as_ref(),
+            _ => self.widget.as_ref(),
+        }
     }
 
     pub async fn draw_or_replace(&mut self, context: &Context, rectangle: &Rectangle) {
-        if let Err(e) = self.0.draw(context, rectangle) {
-            self.replace(e).await;
-            self.0.draw(context, rectangle).unwrap();
+        if let Err(e) = self.displayed().draw(context, rectangle) {
+            self.fail_frame(e).await;
         }
     }
 
     pub async fn size_or_replace(&mut self, context: &Context) -> Size {
-        match self.0.size(context) {
+        if self.hidden {
+            return Size::Static(0);
+        }
+        match self.displayed().size(context) {
             Ok(s) => s,
             Err(e) => {
-                self.replace(e).await;
-                self.0.size(context).unwrap()
+                self.fail_frame(e).await;
+                Size::Static(0)
             }
         }
     }
 
     pub async fn setup_or_replace(&mut self, info: &StatusBarInfo) {
-        match self.0.setup(info) {
-            Ok(s) => s,
+        if let Err(e) = self.widget.setup(info) {
+            // there is no way to retry a setup, the widget can't work without it
+            self.replace(e).await;
+        }
+    }
+
+    pub async fn update_or_replace(&mut self) {
+        if self.next_retry.is_some_and(|retry| Instant::now() < retry) {
+            // backing off, the retry is already scheduled
+            return;
+        }
+        self.frame_failed = false;
+        match self.widget.update().await {
+            Ok(_) if self.failures > 0 => {
+                self.failures = 0;
+                self.last_error = None;
+                self.next_retry = None;
+                self.dirty = true;
+            }
+            Ok(changed) => self.dirty |= changed,
             Err(e) => {
-                self.replace(e).await;
-                self.0.setup(info).unwrap()
+                if !self.fail(e).await {
+                    self.schedule_retry();
+                }
             }
         }
     }
-    pub async fn update_or_replace(&mut self) {
-        if let Err(e) = self.0.update().await {
+
+    pub async fn hook_or_replace(&mut self, sender: HookSender, pool: &mut TimedHooks) {
+        self.sender = Some(sender.clone());
+        if let Err(e) = self.widget.hook(sender, pool).await {
+            // without its hook the widget would never be updated again
             self.replace(e).await;
-            self.0.update().await.unwrap();
         }
     }
 
-    pub async fn hook_or_replace(&mut self, sender: HookSender, pool: &mut TimedHooks) {
-        if let Err(e) = self.0.hook(sender.clone(), pool).await {
+    pub async fn on_click_or_replace(&mut self, button: MouseButton, x: u32, y: u32) {
+        if let Err(e) = self.widget.on_click(button, x, y).await {
+            self.report(e);
+        }
+    }
+
+    pub async fn popup_or_replace(&mut self, button: MouseButton) -> Option<Box<dyn Popup>> {
+        match self.widget.popup(button).await {
+            Ok(popup) => popup,
+            Err(e) => {
+                self.report(e);
+                None
+            }
+        }
+    }
+
+    pub async fn on_release_or_replace(&mut self, button: MouseButton, x: u32, y: u32) {
+        if let Err(e) = self.widget.on_release(button, x, y).await {
+            self.report(e);
+        }
+    }
+
+    /// Records a failure, returns true if the widget has been replaced
+    async fn fail(&mut self, e: WidgetError) -> bool {
+        let policy = self.policy();
+        self.failures += 1;
+        self.dirty = true;
+        warn!(
+            "{} failed ({}/{}): {}",
+            self.name(),
+            self.failures,
+            policy.max_failures,
+            e
+        );
+        if self.failures >= policy.max_failures {
             self.replace(e).await;
-            self.0.hook(sender, pool).await.unwrap();
+            return true;
+        }
+        self.last_error = Some(e.to_string());
+        if let (None, Some(text)) = (&self.placeholder, policy.placeholder) {
+            self.placeholder = Some(Text::new(text, &self.config()).await);
+        }
+        false
+    }
+
+    /// Records a draw or size failure, only the first one until the next update counts
+    async fn fail_frame(&mut self, e: WidgetError) {
+        if self.frame_failed {
+            self.last_error = Some(e.to_string());
+            return;
+        }
+        self.frame_failed = true;
+        self.fail(e).await;
+    }
+
+    /// Logs the failure of an action of the user, a broken button doesn't replace the widget
+    fn report(&self, e: WidgetError) {
+        warn!("{} failed: {}", self.name(), e);
+    }
+
+    fn schedule_retry(&mut self) {
+        let backoff = self.policy().backoff(self.failures);
+        self.next_retry = Some(Instant::now() + backoff);
+        if let Some(sender) = self.sender.clone() {
+            spawn(async move {
+                sleep(backoff).await;
+                // the bar may have been reloaded in the meantime
+                sender.send().await.ok();
+            });
         }
     }
 
     async fn replace(&mut self, e: WidgetError) {
-        error!("{:?}", e);
-        error!("Replacing widget with default");
-        self.0 = Text::new("Widget Crashed 🙃", &WidgetConfig::default()).await;
+        error!("{}: {:?}", self.name(), e);
+        error!("Replacing {} with a placeholder", self.name());
+        let text = self
+            .policy()
+            .placeholder
+            .unwrap_or_else(|| String::from("Widget Crashed 🙃"));
+        // keep the name so that the widget can still be found through the control socket
+        if self.name.is_none() {
+            self.name = Some(self.widget.to_string());
+        }
+        self.widget = Text::new(text, &self.config()).await;
+        self.last_error = Some(e.to_string());
+        self.failures = 0;
+        self.next_retry = None;
+        self.frame_failed = false;
+        self.placeholder = None;
+        self.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::Result;
+    use async_trait::async_trait;
+    use cairo::{Format, ImageSurface};
+
+    /// A widget failing everything but its update if `update_fails` is false
+    #[derive(Debug)]
+    struct Failing {
+        update_fails: bool,
+    }
+
+    impl fmt::Display for Failing {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Failing")
+        }
+    }
+
+    #[async_trait]
+    impl Widget for Failing {
+        fn draw(&self, _context: &Context, _rectangle: &Rectangle) -> Result<()> {
+            Err(WidgetError::Spacer)
+        }
+
+        async fn update(&mut self) -> Result<bool> {
+            if self.update_fails {
+                return Err(WidgetError::Spacer);
+            }
+            Ok(false)
+        }
+
+        async fn on_click(&mut self, _button: MouseButton, _x: u32, _y: u32) -> Result<()> {
+            Err(WidgetError::Spacer)
+        }
+
+        fn size(&self, _context: &Context) -> Result<Size> {
+            Err(WidgetError::Spacer)
+        }
+
+        fn padding(&self) -> u32 {
+            0
+        }
+    }
+
+    fn failing(update_fails: bool, policy: SupervisionPolicy) -> ReplaceableWidget {
+        ReplaceableWidget::new(Box::new(Failing { update_fails })).with_policy(policy)
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_max() {
+        let policy = SupervisionPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(10),
+            ..SupervisionPolicy::default()
+        };
+        let backoffs: Vec<u64> = (1..=6).map(|n| policy.backoff(n).as_secs()).collect();
+        assert_eq!(backoffs, [1, 2, 4, 8, 10, 10]);
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn failed_update_is_retried_after_the_backoff() {
+        let mut wd = failing(
+            true,
+            SupervisionPolicy {
+                initial_backoff: Duration::from_secs(60),
+                ..SupervisionPolicy::default()
+            },
+        );
+        let before = Instant::now();
+        wd.update_or_replace().await;
+        assert_eq!(wd.failures, 1);
+        assert!(wd.last_error().is_some());
+        let retry = wd.next_retry.unwrap();
+        assert!(retry >= before + Duration::from_secs(60));
+
+        // the widget is not updated before the retry
+        wd.update_or_replace().await;
+        assert_eq!(wd.failures, 1);
+        assert_eq!(wd.next_retry, Some(retry));
+    }
+
+    #[tokio::test]
+    async fn replaced_after_max_failures() {
+        let mut wd = failing(
+            true,
+            SupervisionPolicy {
+                initial_backoff: Duration::ZERO,
+                max_failures: 3,
+                ..SupervisionPolicy::default()
+            },
+        );
+        for failures in 1..3 {
+            wd.update_or_replace().await;
+            assert_eq!(wd.failures, failures);
+            assert!(wd.is::<Failing>());
+        }
+        wd.update_or_replace().await;
+        assert!(wd.is::<Text>());
+        assert_eq!(wd.name(), "Failing");
+        assert!(wd.last_error().is_some());
+        assert_eq!(wd.failures, 0);
+    }
+
+    #[tokio::test]
+    async fn draw_failures_count_once_per_update() {
+        let mut wd = failing(
+            false,
+            SupervisionPolicy {
+                max_failures: 3,
+                ..SupervisionPolicy::default()
+            },
+        );
+        let surface = ImageSurface::create(Format::ARgb32, 10, 10).unwrap();
+        let context = Context::new(&surface).unwrap();
+        for _ in 0..10 {
+            wd.size_or_replace(&context).await;
+        }
+        assert_eq!(wd.failures, 1);
+        assert!(wd.is::<Failing>());
+
+        wd.update_or_replace().await;
+        assert_eq!(wd.failures, 0);
+        wd.size_or_replace(&context).await;
+        assert_eq!(wd.failures, 1);
+    }
+
+    #[tokio::test]
+    async fn click_failures_are_not_counted() {
+        let mut wd = failing(
+            false,
+            SupervisionPolicy {
+                max_failures: 3,
+                ..SupervisionPolicy::default()
+            },
+        );
+        for _ in 0..10 {
+            wd.on_click_or_replace(MouseButton::Left, 0, 0).await;
+        }
+        assert_eq!(wd.failures, 0);
+        assert!(wd.is::<Failing>());
     }
 }
@@ -1,12 +1,16 @@
 use crate::{
-    utils::{screen_true_height, Atoms, HookSender, Position, StatusBarInfo, TimedHooks},
+    utils::{Atoms, HookSender, StatusBarInfo, TimedHooks},
     widgets::{Rectangle, Result, Size, Widget, WidgetConfig},
 };
 use async_channel::{bounded, Receiver};
 use async_trait::async_trait;
 use cairo::Context;
 use log::{debug, error};
-use std::{fmt::Display, sync::Arc, thread};
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+    thread,
+};
 use xcb::{
     x::{
         ChangeProperty, ChangeWindowAttributes, ClientMessageData, ClientMessageEvent, Colormap,
@@ -28,6 +32,7 @@ pub struct Systray {
     screen_id: i32,
     children: Vec<Window>,
     event_receiver: Option<Receiver<SystrayEvent>>,
+    hook_sender: Option<Arc<Mutex<HookSender>>>,
     icon_size: u32,
     context: Option<Gcontext>,
 }
@@ -54,6 +59,7 @@ impl Systray {
             screen_id,
             children: Vec::new(),
             event_receiver: None,
+            hook_sender: None,
             internal_padding,
             icon_size: 0,
             context: None,
@@ -136,7 +142,7 @@ impl Systray {
         Ok(())
     }
 
-    fn create_tray_window(&mut self, y: i16, height: u16) -> Result<()> {
+    fn create_tray_window(&mut self, parent: Window, height: u16) -> Result<()> {
         let window: Window = self.connection.generate_id();
         let colormap: Colormap = self.connection.generate_id();
 
@@ -172,9 +178,9 @@ impl Systray {
             .send_and_check_request(&CreateWindow {
                 depth: depth.depth(),
                 wid: window,
-                parent: screen.root(),
+                parent,
                 x: 0,
-                y,
+                y: 0,
                 width: 1,
                 height,
                 border_width: 0,
@@ -352,7 +358,10 @@ impl Widget for Systray {
             }))
             .map_err(Error::from)?;
 
-        if geometry.x() != rectangle.x as i16 || geometry.width() != rectangle.width as u16 {
+        if geometry.x() != rectangle.x as i16
+            || geometry.width() != rectangle.width as u16
+            || geometry.height() != rectangle.height as u16
+        {
             self.connection
                 .send_and_check_request(&ConfigureWindow {
                     window: self.window.unwrap(),
@@ -424,32 +433,41 @@ impl Widget for Systray {
     }
 
     fn setup(&mut self, info: &StatusBarInfo) -> Result<()> {
-        let y = match info.position {
-            Position::Top => 0,
-            Position::Bottom => {
-                screen_true_height(&self.connection, self.screen_id) - info.height as u16
-            }
-        };
-        self.create_tray_window(y as _, info.height as _)?;
         self.icon_size = info.height;
+        if self.window.is_some() {
+            // kept across a reload, the tray window and the selection are still ours
+            return Ok(());
+        }
+        // the tray lives inside the bar so it follows it across monitors
+        self.create_tray_window(info.window, info.height as _)?;
 
         self.take_selection()?;
         Ok(())
     }
 
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating systray");
         let Some(events) = self.event_receiver.take() else {
-            return Ok(());
+            return Ok(false);
         };
+        let mut changed = false;
         while let Ok(event) = events.try_recv() {
             self.handle_event(event)?;
+            changed = true;
         }
         self.event_receiver.replace(events);
-        Ok(())
+        Ok(changed)
     }
 
     async fn hook(&mut self, sender: HookSender, _timed_hooks: &mut TimedHooks) -> Result<()> {
+        if let Some(hook_sender) = &self.hook_sender {
+            // already listening, only notify the reloaded bar from now on
+            *hook_sender.lock().unwrap() = sender;
+            return Ok(());
+        }
+        let hook_sender = Arc::new(Mutex::new(sender));
+        self.hook_sender = Some(Arc::clone(&hook_sender));
+
         let connection = self.connection.clone();
         let (tx, rx) = bounded(10);
         self.event_receiver = Some(rx);
@@ -461,10 +479,14 @@ impl Widget for Systray {
                 None
             };
             if let Some(event) = event {
-                if tx.send_blocking(event).is_err() || sender.send_blocking().is_err() {
+                if tx.send_blocking(event).is_err() {
                     error!("breaking systray hook loop");
                     break;
                 }
+                let sender = hook_sender.lock().unwrap().clone();
+                if sender.send_blocking().is_err() {
+                    debug!("bar is not listening to systray events");
+                }
             }
         });
         Ok(())
@@ -487,6 +509,10 @@ impl Widget for Systray {
 
 impl Drop for Systray {
     fn drop(&mut self) {
+        let Some(tray_window) = self.window else {
+            // never set up, nothing to release
+            return;
+        };
         let setup = self.connection.get_setup();
         let screen = setup.roots().nth(self.screen_id as _).unwrap();
         let root = screen.root();
@@ -515,13 +541,13 @@ impl Drop for Systray {
         }
         self.connection
             .send_and_check_request(&ChangeWindowAttributes {
-                window: self.window.unwrap(),
+                window: tray_window,
                 value_list: &[Cw::EventMask(EventMask::STRUCTURE_NOTIFY)],
             })
             .ok();
         self.connection
             .send_and_check_request(&DestroyWindow {
-                window: self.window.unwrap(),
+                window: tray_window,
             })
             .ok();
         self.connection.flush().ok();
@@ -1,4 +1,4 @@
-use crate::utils::{HookSender, TimedHooks};
+use crate::utils::{HookSender, Template, TimedHooks};
 use crate::{
     widget_default,
     widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
@@ -7,30 +7,38 @@ use async_trait::async_trait;
 use cairo::Context;
 use log::debug;
 use psutil::sensors::temperatures;
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
 /// Displays the average temperature read by the device sensors
 #[derive(Debug)]
 pub struct Temperatures {
-    format: String,
+    format: Template,
     inner: Text,
+    update_interval: Duration,
 }
 
 impl Temperatures {
-    ///* `format`
-    ///  * `%t` will be replaced with the temperature in celsius
+    /// Format used when the config file doesn't set one
+    pub const DEFAULT_FORMAT: &'static str = "{temperature:.1}°C";
+    /// Legacy `%x` tokens of the format
+    pub(crate) const ALIASES: &'static [(&'static str, &'static str)] =
+        &[("%t", "{temperature:.1}")];
+
+    ///* `format` a [Template](crate::utils::format)
+    ///  * `{temperature}` or `%t` the temperature in celsius
     ///* `config` a [&WidgetConfig]
     pub async fn new(format: impl ToString, config: &WidgetConfig) -> Box<Self> {
         Box::new(Self {
-            format: format.to_string(),
+            format: Template::with_aliases(&format.to_string(), Self::ALIASES),
             inner: *Text::new("", config).await,
+            update_interval: config.update_interval,
         })
     }
 }
 
 #[async_trait]
 impl Widget for Temperatures {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating temp");
         let mut temp: f64 = 0.0;
         let mut count: f64 = 0.0;
@@ -38,17 +46,17 @@ impl Widget for Temperatures {
             temp += elem.current().celsius();
             count += 1.0;
         }
-        let text = self.format.replace("%t", &format!("{:.1}", temp / count));
-        self.inner.set_text(text);
-        Ok(())
+        let temp = temp / count;
+        let values = [("temperature", temp.into())];
+        Ok(self.inner.set_level(temp) | self.inner.render(&self.format, &values))
     }
 
     async fn hook(&mut self, sender: HookSender, pool: &mut TimedHooks) -> Result<()> {
-        pool.subscribe(sender);
+        pool.subscribe(self.update_interval, sender);
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for Temperatures {
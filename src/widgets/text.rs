@@ -1,10 +1,11 @@
 use crate::{
-    utils::{set_source_rgba, Color},
+    utils::{format::Value, set_source_rgba, Color, Style, Template, Thresholds},
     widgets::{Rectangle, Result, Size, Widget, WidgetConfig},
 };
 use async_trait::async_trait;
 use cairo::Context;
-use pango::{FontDescription, Layout};
+use log::debug;
+use pango::{glib::markup_escape_text, parse_markup, FontDescription, Layout};
 use pangocairo::{create_context, show_layout};
 use std::fmt::Display;
 
@@ -17,6 +18,11 @@ pub struct Text {
     font: String,
     font_size: f64,
     flex: bool,
+    markup: bool,
+    fg_thresholds: Thresholds,
+    bg_thresholds: Thresholds,
+    level: Option<f64>,
+    style: Style,
 }
 
 impl Text {
@@ -30,11 +36,74 @@ impl Text {
             font: config.font.clone(),
             font_size: config.font_size,
             flex: config.flex,
+            markup: config.markup,
+            fg_thresholds: config.fg_thresholds.clone(),
+            bg_thresholds: config.bg_thresholds.clone(),
+            level: None,
+            style: config.style.clone(),
         })
     }
 
-    pub fn set_text(&mut self, text: impl ToString) {
-        self.text = text.to_string();
+    /// Returns true if the text changed
+    pub fn set_text(&mut self, text: impl ToString) -> bool {
+        let text = text.to_string();
+        if self.text == text {
+            return false;
+        }
+        self.text = text;
+        true
+    }
+
+    /// Sets the text to `template` filled with `values`, escaped if the text is markup,
+    /// returns true if the text changed
+    pub fn render(&mut self, template: &Template, values: &[(&str, Value)]) -> bool {
+        let text = template.render_escaped(values, |value| self.escape(value));
+        self.set_text(text)
+    }
+
+    /// Sets the value the colors are picked for with the thresholds of the [WidgetConfig],
+    /// returns true if the colors changed
+    pub fn set_level(&mut self, value: f64) -> bool {
+        let colors = (self.fg_color(), self.bg_color());
+        self.level = Some(value);
+        colors != (self.fg_color(), self.bg_color())
+    }
+
+    fn fg_color(&self) -> Color {
+        self.level
+            .and_then(|value| self.fg_thresholds.color(value))
+            .unwrap_or(self.fg_color)
+    }
+
+    fn bg_color(&self) -> Option<Color> {
+        self.level.and_then(|value| self.bg_thresholds.color(value))
+    }
+
+    /// Escapes a value before it is substituted in the text, so that it is never parsed as markup
+    pub fn escape(&self, value: &str) -> String {
+        if self.markup {
+            markup_escape_text(value).to_string()
+        } else {
+            value.to_owned()
+        }
+    }
+
+    fn set_layout_text(&self, layout: &Layout) {
+        if !self.markup {
+            layout.set_text(&self.text);
+            return;
+        }
+        match parse_markup(&self.text, '\0') {
+            Ok((attributes, text, _)) => {
+                layout.set_text(&text);
+                layout.set_attributes(Some(&attributes));
+            }
+            Err(e) => {
+                // show the text as is rather than nothing
+                debug!("Invalid markup {:?}: {}", self.text, e);
+                layout.set_text(&self.text);
+            }
+        }
     }
 
     fn get_layout(&self, context: &Context) -> Result<Layout> {
@@ -50,13 +119,14 @@ impl Text {
 #[async_trait]
 impl Widget for Text {
     fn draw(&self, context: &Context, rectangle: &Rectangle) -> Result<()> {
-        set_source_rgba(context, self.fg_color);
+        set_source_rgba(context, self.fg_color());
         let layout = self.get_layout(context)?;
+        // the text overflows both edges when it is taller than the block
         context.move_to(
             f64::from(self.padding),
-            f64::from((rectangle.height - layout.pixel_size().1 as u32) / 2),
+            (f64::from(rectangle.height) - f64::from(layout.pixel_size().1)) / 2.0,
         );
-        layout.set_text(&self.text);
+        self.set_layout_text(&layout);
         show_layout(context, &layout);
         Ok(())
     }
@@ -66,7 +136,7 @@ impl Widget for Text {
             return Ok(Size::Flex);
         }
         let layout = self.get_layout(context)?;
-        layout.set_text(&self.text);
+        self.set_layout_text(&layout);
         let size = 2 * self.padding() + layout.pixel_size().0 as u32;
         Ok(Size::Static(size))
     }
@@ -78,6 +148,17 @@ impl Widget for Text {
             self.padding
         }
     }
+
+    fn as_text_mut(&mut self) -> Option<&mut Text> {
+        Some(self)
+    }
+
+    fn style(&self) -> Style {
+        Style {
+            background: self.bg_color().or(self.style.background),
+            ..self.style.clone()
+        }
+    }
 }
 
 impl Display for Text {
@@ -91,3 +172,35 @@ pub enum Error {
     #[error("Pango error")]
     PangoError,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cairo::{Format, ImageSurface};
+
+    #[tokio::test]
+    async fn draw_text_taller_than_the_block() {
+        let config = WidgetConfig {
+            font_size: 30.0,
+            padding: 0,
+            fg_color: Color::new(1.0, 1.0, 1.0, 1.0),
+            ..WidgetConfig::default()
+        };
+        let text = Text::new("HHHH", &config).await;
+        let mut surface = ImageSurface::create(Format::ARgb32, 100, 6).unwrap();
+        let rectangle = Rectangle {
+            x: 0,
+            y: 0,
+            width: 100,
+            height: 6,
+        };
+        {
+            let context = Context::new(&surface).unwrap();
+            assert!(text.get_layout(&context).unwrap().pixel_size().1 > 6);
+            text.draw(&context, &rectangle).unwrap();
+        }
+        // the middle of the text is drawn in the block
+        let data = surface.data().unwrap();
+        assert!(data.iter().any(|&byte| byte != 0));
+    }
+}
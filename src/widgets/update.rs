@@ -1,11 +1,13 @@
 use crate::{
-    utils::{HookSender, StatusBarInfo, TimedHooks},
+    statusbar::Popup,
+    utils::{HookSender, MouseButton, StatusBarInfo, TimedHooks},
     widget_default,
-    widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
+    widgets::{ListPopup, Rectangle, Result, Text, Widget, WidgetConfig},
 };
 use async_trait::async_trait;
 use cairo::Context;
 use log::error;
+use pango::glib::markup_escape_text;
 use std::{fmt::Display, process::Stdio, time::Duration};
 use tokio::{
     io::{AsyncBufReadExt, BufReader},
@@ -14,10 +16,14 @@ use tokio::{
     time::sleep,
 };
 
+/// Displays the sources with updates, a left click lists the upgradable packages
 #[derive(Debug)]
 pub struct Update {
     inner: Text,
     sources: Vec<Box<dyn UpdateSource>>,
+    /// message and packages of the sources with updates
+    pending: Vec<(String, Vec<String>)>,
+    popup_config: WidgetConfig,
 }
 
 impl Update {
@@ -25,6 +31,8 @@ impl Update {
         Box::new(Self {
             inner: *Text::new("", config).await,
             sources,
+            pending: Vec::new(),
+            popup_config: config.clone(),
         })
     }
 }
@@ -35,15 +43,51 @@ impl Widget for Update {
         Ok(())
     }
 
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         let mut all_updates = Vec::new();
+        self.pending.clear();
         for source in &mut self.sources {
             if source.update_available().await? {
-                all_updates.push(source.message());
+                all_updates.push(self.inner.escape(&source.message()));
+                self.pending.push((source.message(), source.packages()));
             }
         }
-        self.inner.set_text(all_updates.join(" - "));
-        Ok(())
+        Ok(self.inner.set_text(all_updates.join(" - ")))
+    }
+
+    /// The pending packages of every source
+    fn tooltip(&self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let sections: Vec<String> = self
+            .pending
+            .iter()
+            .map(|(message, packages)| {
+                let mut section = format!("<b>{}</b>", markup_escape_text(message));
+                for package in packages {
+                    section.push('\n');
+                    section.push_str(&markup_escape_text(package));
+                }
+                section
+            })
+            .collect();
+        Some(sections.join("\n\n"))
+    }
+
+    async fn popup(&mut self, button: MouseButton) -> Result<Option<Box<dyn Popup>>> {
+        if button != MouseButton::Left {
+            return Ok(None);
+        }
+        let mut items = Vec::new();
+        for (message, packages) in &self.pending {
+            items.push(format!("<b>{}</b>", markup_escape_text(message)));
+            items.extend(packages.iter().map(|p| markup_escape_text(p).to_string()));
+        }
+        if items.is_empty() {
+            items.push(String::from("No pending updates"));
+        }
+        Ok(Some(Box::new(ListPopup::new(items, &self.popup_config))))
     }
 
     async fn hook(&mut self, sender: HookSender, _pool: &mut TimedHooks) -> Result<()> {
@@ -58,7 +102,7 @@ impl Widget for Update {
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for Update {
@@ -71,14 +115,22 @@ impl Display for Update {
 pub trait UpdateSource: std::fmt::Debug + Send {
     async fn update_available(&mut self) -> Result<bool>;
     fn message(&self) -> String;
+    /// Names of the packages found by the last [UpdateSource::update_available]
+    fn packages(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 #[derive(Debug)]
-pub struct Apt {}
+pub struct Apt {
+    packages: Vec<String>,
+}
 
 impl Apt {
     pub fn new() -> Box<Self> {
-        Box::new(Self {})
+        Box::new(Self {
+            packages: Vec::new(),
+        })
     }
 }
 
@@ -92,18 +144,28 @@ impl UpdateSource for Apt {
             .spawn()
             .map_err(Error::from)?;
 
-        child.wait().await.unwrap();
         let stdout = child.stdout.take().unwrap();
         let mut lines = BufReader::new(stdout).lines();
+        // the first line is "Listing..."
         let _ = lines.next_line().await;
-        let line = lines.next_line().await.map_err(Error::from)?;
+        self.packages.clear();
+        while let Some(line) = lines.next_line().await.map_err(Error::from)? {
+            if let Some((package, _)) = line.split_once('/') {
+                self.packages.push(package.to_owned());
+            }
+        }
+        child.wait().await.map_err(Error::from)?;
 
-        Ok(line.is_some())
+        Ok(!self.packages.is_empty())
     }
 
     fn message(&self) -> String {
         "apt".to_string()
     }
+
+    fn packages(&self) -> Vec<String> {
+        self.packages.clone()
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
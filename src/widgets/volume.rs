@@ -1,17 +1,35 @@
 use crate::{
-    utils::{percentage_to_index, HookSender, ResettableTimer, TimedHooks},
+    backend::Osd,
+    statusbar::Popup,
+    utils::{
+        percentage_to_index, HookSender, MouseButton, ResettableTimer, StatusBarInfo, Template,
+        TimedHooks,
+    },
     widget_default,
-    widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
+    widgets::{ListPopup, Rectangle, Result, Text, Widget, WidgetConfig},
 };
 use async_channel::{bounded, Receiver, Sender};
 use async_trait::async_trait;
 use cairo::Context;
-use libpulse_binding::volume::{ChannelVolumes, Volume as PaVolume};
-use log::debug;
-use pulsectl::controllers::DeviceControl;
-use std::{fmt::Display, marker::Send, thread};
+use libpulse_binding::{
+    context::subscribe::{Facility, InterestMaskSet},
+    time::MicroSeconds,
+    volume::{ChannelVolumes, Volume as PaVolume},
+};
+use log::{debug, warn};
+use pango::glib::markup_escape_text;
+use pulsectl::{
+    controllers::{types::DeviceInfo, DeviceControl, SinkController, SourceController},
+    Handler,
+};
+use std::{cell::Cell, fmt::Display, marker::Send, rc::Rc, sync::mpsc, thread, time::Duration};
 
-/// Icons used by [Volume]
+/// Volume change of a scroll step, in percent
+const SCROLL_STEP: f64 = 5.0;
+/// Delay between the attempts to reconnect to pulseaudio, the widget is polled meanwhile
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Icons used by [Volume] and [Microphone](crate::widgets::Microphone)
 #[derive(Debug)]
 pub struct VolumeIcons {
     pub percentages: Vec<String>,
@@ -28,24 +46,51 @@ impl Default for VolumeIcons {
         }
     }
 }
-/// Displays status and volume of the audio device
+
+impl VolumeIcons {
+    /// Default icons of [Microphone](crate::widgets::Microphone)
+    pub fn microphone() -> Self {
+        Self {
+            percentages: vec![String::from('\u{f130}')],
+            muted: String::from('\u{f131}'),
+        }
+    }
+}
+
+/// Displays status and volume of the audio device,
+/// a right click opens a list of the devices to choose the default one
 #[derive(Debug)]
 pub struct Volume {
-    format: String,
+    format: Template,
     inner: Text,
+    update_interval: Duration,
     provider: Box<dyn VolumeProvider>,
     icons: VolumeIcons,
-    previous_volume: f64,
-    previous_muted: bool,
+    /// `None` until the first update
+    previous: Option<VolumeStatus>,
     show_counter: ResettableTimer,
+    sender: Option<HookSender>,
+    /// used to create the [Osd] if it is enabled
+    osd_config: Option<WidgetConfig>,
+    osd: Option<Osd>,
+    popup_config: WidgetConfig,
+    /// names of the devices chosen in the popup, set as default by the next update
+    chosen: (Sender<String>, Receiver<String>),
 }
 
 impl Volume {
-    ///* `format`
-    ///  * *%p* will be replaced with the volume percentage
-    ///  * *%i* will be replaced with the correct icon
-    ///* `volume_command` a function that returns the volume in a range from 0 to 100
-    ///* `muted_command` a function that returns true if the volume is muted
+    /// Format used when the config file doesn't set one
+    pub const DEFAULT_FORMAT: &'static str = "{icon} {percent:.1}";
+    /// Legacy `%x` tokens of the format
+    pub(crate) const ALIASES: &'static [(&'static str, &'static str)] =
+        &[("%p", "{percent:.1}"), ("%i", "{icon}")];
+
+    ///* `format` a [Template](crate::utils::format), replaced by the muted icon while muted
+    ///  * *{percent}* or *%p* the volume percentage
+    ///  * *{icon}* or *%i* the correct icon
+    ///  * *{device}* the description of the audio device
+    ///  * *{port}* the description of its active port, e.g. headphones or speakers
+    ///* `provider` a [VolumeProvider]
     ///* `icons` sets a custom [VolumeIcons]
     ///* `config` a [&WidgetConfig]
     pub async fn new(
@@ -55,55 +100,132 @@ impl Volume {
         config: &WidgetConfig,
     ) -> Box<Self> {
         Box::new(Self {
-            format: format.to_string(),
+            format: Template::with_aliases(&format.to_string(), Self::ALIASES),
             provider,
             icons: icons.unwrap_or_default(),
-            previous_volume: 0.0,
-            previous_muted: false,
+            previous: None,
             show_counter: ResettableTimer::new(config.hide_timeout),
+            sender: None,
+            osd_config: config.osd.then(|| config.clone()),
+            osd: None,
+            popup_config: config.clone(),
+            chosen: bounded(1),
             inner: *Text::new("", config).await,
+            update_interval: config.update_interval,
         })
     }
 
-    fn build_string(&mut self, volume: f64, muted: bool) -> String {
+    fn icon(&self, status: &VolumeStatus) -> &str {
+        if status.muted {
+            return &self.icons.muted;
+        }
+        let percentages_len = self.icons.percentages.len();
+        let index = percentage_to_index(status.volume, (0, percentages_len - 1));
+        &self.icons.percentages[index]
+    }
+
+    /// Returns true if the text changed
+    fn render(&mut self, status: &VolumeStatus) -> bool {
         if self.show_counter.is_done() {
-            return String::from("");
+            return self.inner.set_text("");
         }
-        if muted {
-            return self.icons.muted.clone();
+        if status.muted {
+            let icon = self.inner.escape(&self.icons.muted);
+            return self.inner.set_text(icon);
         }
-        let percentages_len = self.icons.percentages.len();
-        let index = percentage_to_index(volume, (0, percentages_len - 1));
-        self.format
-            .replace("%p", &format!("{:.1}", volume))
-            .replace("%i", &self.icons.percentages[index].to_string())
+        let values = [
+            ("percent", status.volume.into()),
+            ("icon", self.icon(status).into()),
+            ("device", status.device.clone().unwrap_or_default().into()),
+            ("port", status.port.clone().unwrap_or_default().into()),
+        ];
+        self.inner.render(&self.format, &values)
     }
 }
 
 #[async_trait]
 impl Widget for Volume {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating volume");
-        let f = self.provider.volume_and_muted();
-        let (volume, muted) = f.await.unwrap_or((0.0, false));
+        while let Ok(name) = self.chosen.1.try_recv() {
+            self.provider
+                .set_default_device(&name)
+                .await
+                .ok_or(Error::DefaultDevice(name))?;
+        }
+        let f = self.provider.status();
+        let status = f.await.unwrap_or_default();
 
-        if self.previous_muted != muted || self.previous_volume != volume {
-            self.previous_muted = muted;
-            self.previous_volume = volume;
-            self.show_counter.reset();
+        if self.previous.as_ref() != Some(&status) {
+            // the initial state is not a change
+            if self.previous.replace(status.clone()).is_some() {
+                if let Some(osd) = &self.osd {
+                    osd.show(self.icon(&status), status.volume);
+                }
+            }
+            // pulseaudio only reports changes, the update hiding the widget is asked for
+            self.show_counter.reset_and_notify(self.sender.clone());
         }
-        let text = self.build_string(volume, muted);
+        Ok(self.inner.set_level(status.volume) | self.render(&status))
+    }
 
-        self.inner.set_text(text);
+    fn setup(&mut self, info: &StatusBarInfo) -> Result<()> {
+        if let Some(config) = &self.osd_config {
+            self.osd = Osd::for_bar(info, config);
+        }
         Ok(())
     }
 
     async fn hook(&mut self, sender: HookSender, timed_hooks: &mut TimedHooks) -> Result<()> {
-        timed_hooks.subscribe(sender);
+        if self.provider.subscribe(sender.clone()) {
+            self.sender = Some(sender);
+        } else {
+            timed_hooks.subscribe(self.update_interval, sender);
+        }
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    /// Scrolling changes the volume, a left click mutes or unmutes it
+    async fn on_click(&mut self, button: MouseButton, _x: u32, _y: u32) -> Result<()> {
+        let done = match button {
+            MouseButton::ScrollUp => self.provider.change_volume(SCROLL_STEP).await,
+            MouseButton::ScrollDown => self.provider.change_volume(-SCROLL_STEP).await,
+            MouseButton::Left => self.provider.toggle_mute().await,
+            _ => return Ok(()),
+        };
+        done.ok_or(Error::Control)?;
+        Ok(())
+    }
+
+    async fn popup(&mut self, button: MouseButton) -> Result<Option<Box<dyn Popup>>> {
+        if button != MouseButton::Right {
+            return Ok(None);
+        }
+        let Some(devices) = self.provider.devices().await else {
+            return Ok(None);
+        };
+        let items = devices
+            .iter()
+            .map(|device| {
+                let description = markup_escape_text(&device.description);
+                if device.default {
+                    format!("<b>{}</b>", description)
+                } else {
+                    description.to_string()
+                }
+            })
+            .collect();
+        let selected = devices.iter().position(|d| d.default).unwrap_or(0);
+        let sender = self.chosen.0.clone();
+        let popup = ListPopup::new(items, &self.popup_config)
+            .selected(selected)
+            .on_select(move |index| {
+                sender.try_send(devices[index].name.clone()).ok();
+            });
+        Ok(Some(Box::new(popup)))
+    }
+
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for Volume {
@@ -112,11 +234,78 @@ impl Display for Volume {
     }
 }
 
+/// An audio device listed by a [VolumeProvider]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioDevice {
+    /// identifies the device
+    pub name: String,
+    /// readable name
+    pub description: String,
+    /// true if it is the default device
+    pub default: bool,
+}
+
+/// State of the audio device displayed by [Volume]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VolumeStatus {
+    /// in a range from 0 to 100
+    pub volume: f64,
+    pub muted: bool,
+    pub device: Option<String>,
+    pub port: Option<String>,
+}
+
+impl From<DeviceInfo> for VolumeStatus {
+    fn from(device: DeviceInfo) -> Self {
+        Self {
+            volume: volume_to_percent(device.volume),
+            muted: device.mute,
+            device: device.description.or(device.name),
+            port: device
+                .active_port
+                .and_then(|port| port.description.or(port.name)),
+        }
+    }
+}
+
 #[async_trait]
-pub trait VolumeProvider: std::fmt::Debug + Send {
+pub trait VolumeProvider: std::fmt::Debug + Send + Sync {
     async fn volume(&self) -> Option<f64>;
     async fn muted(&self) -> Option<bool>;
     async fn volume_and_muted(&self) -> Option<(f64, bool)>;
+    async fn status(&self) -> Option<VolumeStatus> {
+        let (volume, muted) = self.volume_and_muted().await?;
+        Some(VolumeStatus {
+            volume,
+            muted,
+            ..VolumeStatus::default()
+        })
+    }
+    /// Asks `sender` for an update whenever the volume changes,
+    /// returns false if the provider has to be polled instead
+    fn subscribe(&mut self, _sender: HookSender) -> bool {
+        false
+    }
+    /// Sets the volume to `percent`, in a range from 0 to 100
+    async fn set_volume(&self, _percent: f64) -> Option<()> {
+        None
+    }
+    /// Adds `delta` percent to the volume
+    async fn change_volume(&self, delta: f64) -> Option<()> {
+        let volume = self.volume().await?;
+        self.set_volume((volume + delta).clamp(0.0, 100.0)).await
+    }
+    async fn toggle_mute(&self) -> Option<()> {
+        None
+    }
+    /// The devices that can be made the default one
+    async fn devices(&self) -> Option<Vec<AudioDevice>> {
+        None
+    }
+    /// Makes the device called `name` the default one
+    async fn set_default_device(&self, _name: &str) -> Option<()> {
+        None
+    }
 }
 
 fn volume_to_percent(volume: ChannelVolumes) -> f64 {
@@ -127,34 +316,121 @@ fn volume_to_percent(volume: ChannelVolumes) -> f64 {
     (avg - PaVolume::MUTED.0) as f64 / base_delta
 }
 
+fn percent_to_volume(percent: f64) -> PaVolume {
+    let base_delta = (PaVolume::NORMAL.0 as f64 - PaVolume::MUTED.0 as f64) / 100.0;
+    PaVolume(PaVolume::MUTED.0 + (percent.clamp(0.0, 100.0) * base_delta) as u32)
+}
+
+/// Kind of device controlled by a [PulseaudioProvider]
+#[derive(Debug, Clone, Copy)]
+enum DeviceKind {
+    Sink,
+    Source,
+}
+
+impl DeviceKind {
+    fn controller(self) -> Option<Box<dyn DeviceControl<DeviceInfo>>> {
+        let controller: Box<dyn DeviceControl<DeviceInfo>> = match self {
+            Self::Sink => Box::new(SinkController::create().ok()?),
+            Self::Source => Box::new(SourceController::create().ok()?),
+        };
+        Some(controller)
+    }
+
+    fn facility(self) -> Facility {
+        match self {
+            Self::Sink => Facility::Sink,
+            Self::Source => Facility::Source,
+        }
+    }
+
+    fn mask(self) -> InterestMaskSet {
+        match self {
+            Self::Sink => InterestMaskSet::SINK,
+            Self::Source => InterestMaskSet::SOURCE,
+        }
+    }
+}
+
+/// Sent to the thread that owns the pulseaudio connection,
+/// which answers with the volume and mute state of the default device
+#[derive(Debug, Clone, Copy)]
+enum Request {
+    Query,
+    SetVolume(f64),
+    ChangeVolume(f64),
+    ToggleMute,
+}
+
 pub struct PulseaudioProvider {
-    request: Sender<()>,
-    data: Receiver<Option<(f64, bool)>>,
+    kind: DeviceKind,
+    request: Sender<Request>,
+    data: Receiver<Option<VolumeStatus>>,
 }
 
 impl PulseaudioProvider {
+    /// Controls the default sink, the output device
     pub async fn new() -> Result<Self> {
+        Ok(Self::spawn(DeviceKind::Sink))
+    }
+
+    /// Controls the default source, the input device, e.g. the microphone
+    pub async fn source() -> Result<Self> {
+        Ok(Self::spawn(DeviceKind::Source))
+    }
+
+    fn spawn(kind: DeviceKind) -> Self {
         let (request_tx, request_rx) = bounded(10);
         let (data_tx, data_rx) = bounded(10);
         thread::spawn(move || {
-            let mut controller = pulsectl::controllers::SinkController::create().unwrap();
-            while request_rx.recv_blocking().is_ok() {
-                let data = if let Ok(default_device) = controller.get_default_device() {
-                    Some((
-                        volume_to_percent(default_device.volume),
-                        default_device.mute,
-                    ))
-                } else {
-                    None
+            let mut controller = kind.controller();
+            if controller.is_none() {
+                warn!("Cannot connect to pulseaudio");
+            }
+            while let Ok(request) = request_rx.recv_blocking() {
+                // the connection is lost when pulseaudio restarts
+                if controller
+                    .as_mut()
+                    .is_none_or(|controller| controller.get_default_device().is_err())
+                {
+                    controller = kind.controller();
+                }
+                let Some(controller) = controller.as_mut() else {
+                    if data_tx.send_blocking(None).is_err() {
+                        break;
+                    }
+                    continue;
                 };
+                if let Ok(mut device) = controller.get_default_device() {
+                    let current = volume_to_percent(device.volume);
+                    let target = match request {
+                        Request::Query | Request::ToggleMute => None,
+                        Request::SetVolume(percent) => Some(percent),
+                        Request::ChangeVolume(delta) => Some(current + delta),
+                    };
+                    if let Some(target) = target {
+                        // keeps the balance between the channels
+                        if let Some(volume) = device.volume.scale(percent_to_volume(target)) {
+                            controller.set_device_volume_by_index(device.index, volume);
+                        }
+                    }
+                    if let Request::ToggleMute = request {
+                        controller.set_device_mute_by_index(device.index, !device.mute);
+                    }
+                }
 
-                data_tx.send_blocking(data).unwrap();
+                let data = controller.get_default_device().ok().map(VolumeStatus::from);
+                // the provider has been dropped with its widget
+                if data_tx.send_blocking(data).is_err() {
+                    break;
+                }
             }
         });
-        Ok(Self {
+        Self {
+            kind,
             request: request_tx,
             data: data_rx,
-        })
+        }
     }
 }
 
@@ -164,26 +440,167 @@ impl std::fmt::Debug for PulseaudioProvider {
     }
 }
 
+impl PulseaudioProvider {
+    async fn send(&self, request: Request) -> Option<VolumeStatus> {
+        self.request.send(request).await.ok()?;
+        self.data.recv().await.ok()?
+    }
+}
+
+/// Asks `sender` for an update when a device of `kind` or the server changes, this includes
+/// the volume, the active port and the default device, returns false if pulseaudio can't be reached
+///
+/// If the connection is lost the widget is polled until pulseaudio is back.
+fn listen_device_events(kind: DeviceKind, sender: HookSender) -> bool {
+    let (ready_tx, ready_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut ready_tx = Some(ready_tx);
+        loop {
+            match Handler::connect("barust") {
+                Ok(handler) => {
+                    match ready_tx.take() {
+                        Some(ready_tx) => {
+                            ready_tx.send(true).ok();
+                        }
+                        // the devices may have changed while disconnected
+                        None if sender.send_blocking().is_err() => return,
+                        None => {}
+                    }
+                    if !watch_device_events(&handler, kind, &sender) {
+                        return;
+                    }
+                    warn!("Lost the connection to pulseaudio, reconnecting");
+                }
+                Err(e) => {
+                    if let Some(ready_tx) = ready_tx.take() {
+                        warn!("Cannot subscribe to pulseaudio events: {}", e);
+                        ready_tx.send(false).ok();
+                        return;
+                    }
+                    debug!("Cannot reconnect to pulseaudio: {}", e);
+                }
+            }
+            thread::sleep(RECONNECT_DELAY);
+            // polls the widget while pulseaudio is away, the widget is gone if nobody listens
+            if sender.send_blocking().is_err() {
+                return;
+            }
+        }
+    });
+    ready_rx.recv().unwrap_or(false)
+}
+
+/// Forwards the events of `handler` to `sender`, returns true if the connection is lost
+/// and false once the bar stops listening
+fn watch_device_events(handler: &Handler, kind: DeviceKind, sender: &HookSender) -> bool {
+    let changed = Rc::new(Cell::new(false));
+    {
+        let changed = changed.clone();
+        let mut context = handler.context.borrow_mut();
+        context.set_subscribe_callback(Some(Box::new(move |facility, _, _| {
+            if facility == Some(kind.facility()) || facility == Some(Facility::Server) {
+                changed.set(true);
+            }
+        })));
+        context.subscribe(kind.mask() | InterestMaskSet::SERVER, |_| {});
+    }
+
+    let mut mainloop = handler.mainloop.borrow_mut();
+    loop {
+        // wakes up regularly to notice a reload even if pulseaudio is quiet
+        let result = mainloop
+            .prepare(Some(MicroSeconds::SECOND))
+            .and_then(|_| mainloop.poll())
+            .and_then(|_| mainloop.dispatch());
+        if result.is_err() || !handler.context.borrow().get_state().is_good() {
+            return true;
+        }
+        if changed.replace(false) {
+            if sender.send_blocking().is_err() {
+                return false;
+            }
+        } else if sender.is_closed() {
+            return false;
+        }
+    }
+}
+
 #[async_trait]
 impl VolumeProvider for PulseaudioProvider {
     async fn volume(&self) -> Option<f64> {
-        self.request.send(()).await.ok()?;
-        self.data.recv().await.ok()?.map(|(v, _)| v)
+        self.send(Request::Query).await.map(|s| s.volume)
     }
 
     async fn muted(&self) -> Option<bool> {
-        self.request.send(()).await.ok()?;
-        self.data.recv().await.ok()?.map(|(_, m)| m)
+        self.send(Request::Query).await.map(|s| s.muted)
     }
 
     async fn volume_and_muted(&self) -> Option<(f64, bool)> {
-        self.request.send(()).await.ok()?;
-        self.data.recv().await.ok()?
+        self.send(Request::Query).await.map(|s| (s.volume, s.muted))
+    }
+
+    async fn status(&self) -> Option<VolumeStatus> {
+        self.send(Request::Query).await
+    }
+
+    fn subscribe(&mut self, sender: HookSender) -> bool {
+        listen_device_events(self.kind, sender)
+    }
+
+    async fn set_volume(&self, percent: f64) -> Option<()> {
+        self.send(Request::SetVolume(percent)).await.map(|_| ())
+    }
+
+    async fn change_volume(&self, delta: f64) -> Option<()> {
+        self.send(Request::ChangeVolume(delta)).await.map(|_| ())
+    }
+
+    async fn toggle_mute(&self) -> Option<()> {
+        self.send(Request::ToggleMute).await.map(|_| ())
+    }
+
+    async fn devices(&self) -> Option<Vec<AudioDevice>> {
+        let kind = self.kind;
+        tokio::task::spawn_blocking(move || {
+            let mut controller = kind.controller()?;
+            let default = controller.get_default_device().ok()?.name;
+            let devices = controller.list_devices().ok()?;
+            let devices = devices
+                .into_iter()
+                // the monitors of the sinks are not microphones
+                .filter(|device| matches!(kind, DeviceKind::Sink) || device.monitor.is_none())
+                .filter_map(|device| {
+                    let name = device.name?;
+                    Some(AudioDevice {
+                        description: device.description.unwrap_or_else(|| name.clone()),
+                        default: default.as_ref() == Some(&name),
+                        name,
+                    })
+                })
+                .collect();
+            Some(devices)
+        })
+        .await
+        .ok()?
+    }
+
+    async fn set_default_device(&self, name: &str) -> Option<()> {
+        let (kind, name) = (self.kind, name.to_owned());
+        tokio::task::spawn_blocking(move || {
+            let mut controller = kind.controller()?;
+            controller.set_default_device(&name).ok()?.then_some(())
+        })
+        .await
+        .ok()?
     }
 }
 
 #[derive(thiserror::Error, Debug)]
-#[error(transparent)]
 pub enum Error {
+    #[error(transparent)]
     Psutil(#[from] psutil::Error),
+    #[error("Cannot control the volume of the default device")]
+    Control,
+    #[error("Cannot make {0} the default device")]
+    DefaultDevice(String),
 }
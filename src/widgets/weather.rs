@@ -1,5 +1,5 @@
 use crate::{
-    utils::{HookSender, TimedHooks},
+    utils::{HookSender, Template, TimedHooks},
     widget_default,
     widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
 };
@@ -8,6 +8,7 @@ use cairo::Context;
 use ipgeolocate::{GeoError, Locator, Service};
 use log::debug;
 use open_meteo_api::models::TimeZone;
+use pango::glib::markup_escape_text;
 use std::fmt::Debug;
 use std::time::Duration;
 use tokio::time::sleep;
@@ -19,6 +20,17 @@ pub struct Meteo {
     pub current: String,
     pub max: String,
     pub min: String,
+    /// the next days, starting from today
+    pub forecast: Vec<Forecast>,
+}
+
+/// Weather expected for a day
+#[derive(Debug)]
+pub struct Forecast {
+    pub date: String,
+    pub code: f32,
+    pub max: String,
+    pub min: String,
 }
 
 #[derive(Debug)]
@@ -84,12 +96,33 @@ impl WeatherProvider for OpenMeteoProvider {
             current_weather.temperature, daily_units.temperature_2m_min
         );
 
+        let forecast = daily
+            .time
+            .iter()
+            .zip(&daily.weathercode)
+            .zip(
+                daily
+                    .temperature_2m_max
+                    .iter()
+                    .zip(&daily.temperature_2m_min),
+            )
+            .filter_map(|((date, code), (max, min))| {
+                Some(Forecast {
+                    date: date.clone(),
+                    code: (*code)?,
+                    max: format!("{}{}", (*max)?, daily_units.temperature_2m_max),
+                    min: format!("{}{}", (*min)?, daily_units.temperature_2m_min),
+                })
+            })
+            .collect();
+
         let out = Meteo {
             code: current_weather.weathercode,
             city: loc_info.city,
             current,
             max,
             min,
+            forecast,
         };
         Ok(out)
     }
@@ -161,18 +194,30 @@ pub trait WeatherProvider: Send + std::fmt::Debug {
 #[derive(Debug)]
 pub struct Weather {
     icons: MeteoIcons,
-    format: String,
+    format: Template,
     inner: Text,
     provider: Box<dyn WeatherProvider>,
+    forecast: Option<String>,
 }
 
 impl Weather {
-    ///* `format`
-    ///  * `%city` will be replaced with the current city used as reference for the meteo
-    ///  * `%icon` will be replaced with the current symbol for the weather
-    ///  * `%cur` will be replaced with the current temperature
-    ///  * `%max` will be replaced with the max temperature
-    ///  * `%min` will be replaced with the min temperature
+    /// Format used when the config file doesn't set one
+    pub const DEFAULT_FORMAT: &'static str = "{city} {icon} {current}";
+    /// Legacy `%x` tokens of the format
+    pub(crate) const ALIASES: &'static [(&'static str, &'static str)] = &[
+        ("%city", "{city}"),
+        ("%icon", "{icon}"),
+        ("%cur", "{current}"),
+        ("%max", "{max}"),
+        ("%min", "{min}"),
+    ];
+
+    ///* `format` a [Template](crate::utils::format)
+    ///  * `{city}` or `%city` the current city used as reference for the meteo
+    ///  * `{icon}` or `%icon` the current symbol for the weather
+    ///  * `{current}` or `%cur` the current temperature
+    ///  * `{max}` or `%max` the max temperature
+    ///  * `{min}` or `%min` the min temperature
     ///* `icons` a [&MeteoIcons]
     ///* `config` a [&WidgetConfig]
     pub async fn new(
@@ -183,27 +228,50 @@ impl Weather {
     ) -> Box<Self> {
         Box::new(Self {
             icons,
-            format: format.to_string(),
+            format: Template::with_aliases(&format.to_string(), Self::ALIASES),
             inner: *Text::new("Loading...", config).await,
             provider,
+            forecast: None,
         })
     }
 }
 
 #[async_trait]
 impl Widget for Weather {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating meteo");
         let meteo = self.provider.get_current_meteo().await?;
-        let text_str = self
-            .format
-            .replace("%city", &meteo.city.to_string())
-            .replace("%icon", self.icons.translate_code(meteo.code as _))
-            .replace("%cur", &meteo.current)
-            .replace("%max", &meteo.max)
-            .replace("%min", &meteo.min);
-        self.inner.set_text(text_str);
-        Ok(())
+        let values = [
+            ("city", meteo.city.to_string().into()),
+            ("icon", self.icons.translate_code(meteo.code as _).into()),
+            ("current", meteo.current.into()),
+            ("max", meteo.max.into()),
+            ("min", meteo.min.into()),
+        ];
+        let forecast: Vec<String> = meteo
+            .forecast
+            .iter()
+            .map(|day| {
+                format!(
+                    "{}  {}  {} / {}",
+                    day.date,
+                    self.icons.translate_code(day.code as _),
+                    day.max,
+                    day.min
+                )
+            })
+            .collect();
+        self.forecast = Some(format!(
+            "<b>{}</b>\n{}",
+            markup_escape_text(&meteo.city),
+            markup_escape_text(&forecast.join("\n"))
+        ));
+        Ok(self.inner.render(&self.format, &values))
+    }
+
+    /// The forecast of the next days
+    fn tooltip(&self) -> Option<String> {
+        self.forecast.clone()
     }
 
     async fn hook(&mut self, sender: HookSender, _pool: &mut TimedHooks) -> Result<()> {
@@ -220,7 +288,7 @@ impl Widget for Weather {
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    widget_default!(draw, size, padding, style);
 }
 
 impl std::fmt::Display for Weather {
@@ -1,4 +1,4 @@
-use crate::utils::{HookSender, TimedHooks};
+use crate::utils::{HookSender, Template, TimedHooks};
 use crate::{
     widget_default,
     widgets::{Rectangle, Result, Text, Widget, WidgetConfig},
@@ -6,57 +6,69 @@ use crate::{
 use async_trait::async_trait;
 use cairo::Context;
 use log::debug;
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
 /// Displays informations about a network interface
 #[derive(Debug)]
 pub struct Wlan {
-    format: String,
+    format: Template,
     interface: String,
     inner: Text,
+    update_interval: Duration,
 }
 
 impl Wlan {
-    ///* `format`
-    ///  * `%i` will be replaced with the interface name
-    ///  * `%e` will be replaced with the essid
-    ///  * `%q` will be replaced with the signal quality
+    /// Format used when the config file doesn't set one
+    pub const DEFAULT_FORMAT: &'static str = "{essid}";
+    /// Legacy `%x` tokens of the format
+    pub(crate) const ALIASES: &'static [(&'static str, &'static str)] = &[
+        ("%i", "{interface}"),
+        ("%e", "{essid}"),
+        ("%q", "{quality}"),
+    ];
+
+    ///* `format` a [Template](crate::utils::format)
+    ///  * `{interface}` or `%i` the interface name
+    ///  * `{essid}` or `%e` the essid
+    ///  * `{quality}` or `%q` the signal quality
     ///* `interface` name of the network interface
     ///* `fg_color` foreground color
     pub async fn new(format: impl ToString, interface: String, config: &WidgetConfig) -> Box<Self> {
         Box::new(Self {
-            format: format.to_string(),
+            format: Template::with_aliases(&format.to_string(), Self::ALIASES),
             interface,
             inner: *Text::new("", config).await,
+            update_interval: config.update_interval,
         })
     }
 
-    fn build_string(&self) -> String {
+    /// Returns true if the text changed
+    fn render(&mut self) -> bool {
         let Some(data) = iwlib::get_wireless_info(self.interface.clone()) else {
-            return String::from("No interface");
+            return self.inner.set_text("No interface");
         };
-        self.format
-            .replace("%i", &self.interface)
-            .replace("%e", &data.wi_essid)
-            .replace("%q", &data.wi_quality.to_string())
+        let values = [
+            ("interface", self.interface.as_str().into()),
+            ("essid", data.wi_essid.into()),
+            ("quality", f64::from(data.wi_quality).into()),
+        ];
+        self.inner.render(&self.format, &values)
     }
 }
 
 #[async_trait]
 impl Widget for Wlan {
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating wlan");
-        let text = self.build_string();
-        self.inner.set_text(text);
-        Ok(())
+        Ok(self.render())
     }
 
     async fn hook(&mut self, sender: HookSender, timed_hooks: &mut TimedHooks) -> Result<()> {
-        timed_hooks.subscribe(sender);
+        timed_hooks.subscribe(self.update_interval, sender);
         Ok(())
     }
 
-    widget_default!(draw, size, padding);
+    widget_default!(draw, size, padding, style);
 }
 
 impl Display for Wlan {
@@ -1,5 +1,5 @@
 use crate::{
-    utils::{set_source_rgba, Atoms, Color, HookSender, TimedHooks},
+    utils::{set_source_rgba, Atoms, Color, HookSender, Style, TimedHooks},
     widgets::{Rectangle, Result, Size, Widget, WidgetConfig},
 };
 use async_trait::async_trait;
@@ -47,6 +47,7 @@ pub struct Workspaces {
     policy: Box<dyn WorkspaceHider>,
     status_provider: Box<dyn WorkspaceStatusProvider>,
     workspaces: Vec<(String, WorkspaceStatus)>,
+    style: Style,
 }
 
 impl Workspaces {
@@ -70,6 +71,7 @@ impl Workspaces {
             font_size: config.font_size,
             policy: Box::new(policy),
             status_provider: Box::new(status_provider),
+            style: config.style.clone(),
         })
     }
 
@@ -104,7 +106,7 @@ impl Widget for Workspaces {
                 first = false;
                 context.rel_move_to(
                     0.0,
-                    f64::from((rectangle.height - layout.pixel_size().1 as u32) / 2),
+                    (f64::from(rectangle.height) - f64::from(layout.pixel_size().1)) / 2.0,
                 );
             }
             show_layout(context, &layout);
@@ -116,14 +118,14 @@ impl Widget for Workspaces {
         Ok(())
     }
 
-    async fn update(&mut self) -> Result<()> {
+    async fn update(&mut self) -> Result<bool> {
         debug!("updating workspaces");
         let (connection, _) = Connection::connect(None).map_err(Error::from)?;
         let Ok(workspaces) = get_desktops_names(&connection) else {
-            return Ok(());
+            return Ok(false);
         };
 
-        self.workspaces.clear();
+        let previous = std::mem::take(&mut self.workspaces);
 
         self.status_provider.update().await?;
         for (i, workspace) in workspaces.into_iter().enumerate() {
@@ -132,7 +134,7 @@ impl Widget for Workspaces {
             self.workspaces.push((workspace, new_status));
         }
 
-        Ok(())
+        Ok(previous != self.workspaces)
     }
 
     async fn hook(&mut self, sender: HookSender, _timed_hooks: &mut TimedHooks) -> Result<()> {
@@ -190,6 +192,10 @@ impl Widget for Workspaces {
     fn padding(&self) -> u32 {
         self.padding
     }
+
+    fn style(&self) -> Style {
+        self.style.clone()
+    }
 }
 
 impl Display for Workspaces {